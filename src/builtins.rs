@@ -1,7 +1,9 @@
 use crate::eval::Scope;
-use crate::function::ParameterList;
+use crate::format::format_template;
+use crate::function::{ArgumentList, Function, ParameterList};
 use crate::manual::{increment_manual_id, MANUAL_ID, BASICS, DATABASES, DATABASES_TEST2};
-use crate::object::Object;
+use crate::object::{vec_to_list, Object};
+use crate::parser::AstNode;
 use colored::Colorize;
 use crate::error::SantaError;
 use crate::database::{get_db_builtins, ACCESSED_DB};
@@ -9,6 +11,7 @@ use std::process::exit;
 use std::cell::RefCell;
 use std::rc::Rc;
 use crate::networking::get_network_builtins;
+use unicode_segmentation::UnicodeSegmentation;
 
 fn builtin_print(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
     if unsafe { MANUAL_ID } == BASICS {
@@ -61,15 +64,30 @@ fn builtin_list_push(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
     Ok(Object::None)
 }
 
+fn builtin_list_pop(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
+    if let Some(Object::List(list)) = scope.borrow().get_variable(&"list".into()) {
+        let value = list.borrow_mut().pop();
+        value.ok_or(SantaError::IndexOutOfBounds)
+    } else {
+        Err(SantaError::InvalidOperationError {cause: "pop can only be used on a list".into()})
+    }
+}
+
 fn builtin_len(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
     if let Some(obj) = scope
         .borrow()
         .get_variable(&"value".into()) {
 
         Ok(Object::Integer(match obj {
-            Object::String(s) => s.len(),
+            // Like indexing, the length of a string counts unicode characters and not bytes
+            Object::String(s) => s.chars().count(),
             Object::List(l) => l.borrow().len(),
             Object::Map(m) => m.borrow().len(),
+            Object::Range(start, stop, step) => {
+                let (start, stop, step) = (start as i128, stop as i128, step as i128);
+                let span = if step > 0 { stop - start } else { start - stop };
+                if span > 0 { ((span - 1) / step.abs() + 1) as usize } else { 0 }
+            },
             i =>  return Err(SantaError::InvalidOperationError {cause: format!("length of {} not defined", i)})
         } as i64))
     } else {
@@ -77,6 +95,109 @@ fn builtin_len(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
     }
 }
 
+fn builtin_chars(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
+    let scope = scope.borrow();
+
+    match (scope.get_variable(&"value".into()), scope.get_variable(&"graphemes".into())) {
+        (Some(Object::String(s)), Some(Object::Boolean(graphemes))) => Ok(vec_to_list(if graphemes {
+            // Characters as a reader sees them, like a letter with its accents
+            s.graphemes(true).map(|i| Object::String(i.into())).collect()
+        } else {
+            s.chars().map(|i| Object::String(i.to_string())).collect()
+        })),
+        _ => Err(SantaError::InvalidOperationError {cause: "The chars function expects a string and optionally a boolean".into()}),
+    }
+}
+
+fn builtin_bytes(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
+    if let Some(Object::String(s)) = scope.borrow().get_variable(&"value".into()) {
+        Ok(vec_to_list(s.bytes().map(|i| Object::Integer(i as i64)).collect()))
+    } else {
+        Err(SantaError::InvalidOperationError {cause: "The bytes function expects a string".into()})
+    }
+}
+
+fn builtin_upper(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
+    if let Some(Object::String(s)) = scope.borrow().get_variable(&"value".into()) {
+        Ok(Object::String(s.to_uppercase()))
+    } else {
+        Err(SantaError::InvalidOperationError {cause: "upper can only be used on a string".into()})
+    }
+}
+
+fn builtin_lower(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
+    if let Some(Object::String(s)) = scope.borrow().get_variable(&"value".into()) {
+        Ok(Object::String(s.to_lowercase()))
+    } else {
+        Err(SantaError::InvalidOperationError {cause: "lower can only be used on a string".into()})
+    }
+}
+
+fn builtin_split(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
+    let scope = scope.borrow();
+    let to_list = |parts: Vec<&str>| vec_to_list(parts.into_iter().map(|i| Object::String(i.into())).collect());
+
+    match (scope.get_variable(&"value".into()), scope.get_variable(&"separator".into())) {
+        // Without a separator, the string is split on whitespace
+        (Some(Object::String(s)), Some(Object::None)) => Ok(to_list(s.split_whitespace().collect())),
+        (Some(Object::String(s)), Some(Object::String(separator))) if !separator.is_empty() => Ok(to_list(s.split(separator.as_str()).collect())),
+        _ => Err(SantaError::InvalidOperationError {cause: "split expects a string and optionally a separator that isn't empty".into()}),
+    }
+}
+
+fn builtin_keys(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
+    if let Some(Object::Map(map)) = scope.borrow().get_variable(&"map".into()) {
+        Ok(vec_to_list(map.borrow().keys().cloned().collect()))
+    } else {
+        Err(SantaError::InvalidOperationError {cause: "keys can only be used on a map".into()})
+    }
+}
+
+fn builtin_values(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
+    if let Some(Object::Map(map)) = scope.borrow().get_variable(&"map".into()) {
+        Ok(vec_to_list(map.borrow().values().cloned().collect()))
+    } else {
+        Err(SantaError::InvalidOperationError {cause: "values can only be used on a map".into()})
+    }
+}
+
+fn builtin_format(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
+    let scope = scope.borrow();
+
+    match (scope.get_variable(&"template".into()), scope.get_variable(&"args".into())) {
+        (Some(Object::String(template)), Some(Object::List(args))) => Ok(Object::String(format_template(&template, &args.borrow())?)),
+        _ => Err(SantaError::InvalidOperationError {cause: "The format function expects a string as template".into()}),
+    }
+}
+
+fn builtin_range(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
+    if let Some(Object::List(lst)) = scope.borrow().get_variable(&"args".into()) {
+        let mut bounds = vec![];
+        for i in lst.borrow().iter() {
+            match i {
+                Object::Integer(i) => bounds.push(*i),
+                Object::Boolean(i) => bounds.push(*i as i64),
+                i => return Err(SantaError::InvalidOperationError {cause: format!("range bound {} is not an integer", i)}),
+            }
+        }
+
+        let (start, stop, step) = match bounds.as_slice() {
+            [stop] => (0, *stop, 1),
+            [start, stop] => (*start, *stop, 1),
+            [start, stop, step] => (*start, *stop, *step),
+            _ => return Err(SantaError::InvalidOperationError {cause: "The range function expects one to three integers".into()}),
+        };
+
+        if step == 0 {
+            return Err(SantaError::InvalidOperationError {cause: "The step of a range can't be zero".into()});
+        }
+
+        Ok(Object::Range(start, stop, step))
+    } else {
+        Err(SantaError::InvalidOperationError {cause: "No args found".into()})
+    }
+}
+
 fn builtin_assert(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
 
     if let Some(Object::Boolean(b)) = scope
@@ -116,12 +237,88 @@ fn builtin_exit(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
     }
 }
 
+type BuiltinFunction = fn(Rc<RefCell<Scope>>) -> Result<Object, SantaError>;
+
+fn chars_parameters() -> ParameterList {
+    let mut parameters = ParameterList::new(vec!["value".into(), "graphemes".into()]);
+    parameters.defaults.insert("graphemes".into(), AstNode::Boolean(false).boxed());
+    parameters
+}
+
+// The method tables of the builtin types. A method is a builtin function that gets the value
+// it is called on as its first argument, so `"abc".upper()` works like `upper("abc")` would.
+
+fn string_method(name: &str) -> Option<(ParameterList, BuiltinFunction)> {
+    let value = || vec!["value".into()];
+    let method: (ParameterList, BuiltinFunction) = match name {
+        "len" => (ParameterList::new(value()), builtin_len),
+        "chars" => (chars_parameters(), builtin_chars),
+        "bytes" => (ParameterList::new(value()), builtin_bytes),
+        "format" => (ParameterList::with_rest(vec!["template".into()], "args"), builtin_format),
+        "upper" => (ParameterList::new(value()), builtin_upper),
+        "lower" => (ParameterList::new(value()), builtin_lower),
+        "split" => {
+            let mut parameters = ParameterList::new(vec!["value".into(), "separator".into()]);
+            parameters.defaults.insert("separator".into(), AstNode::None.boxed());
+            (parameters, builtin_split)
+        }
+        _ => return None,
+    };
+    Some(method)
+}
+
+fn list_method(name: &str) -> Option<(ParameterList, BuiltinFunction)> {
+    let method: (ParameterList, BuiltinFunction) = match name {
+        "len" => (ParameterList::new(vec!["value".into()]), builtin_len),
+        "push" => (ParameterList::new(vec!["list".into(), "value".into()]), builtin_list_push),
+        "pop" => (ParameterList::new(vec!["list".into()]), builtin_list_pop),
+        _ => return None,
+    };
+    Some(method)
+}
+
+fn map_method(name: &str) -> Option<(ParameterList, BuiltinFunction)> {
+    let method: (ParameterList, BuiltinFunction) = match name {
+        "len" => (ParameterList::new(vec!["value".into()]), builtin_len),
+        "keys" => (ParameterList::new(vec!["map".into()]), builtin_keys),
+        "values" => (ParameterList::new(vec!["map".into()]), builtin_values),
+        _ => return None,
+    };
+    Some(method)
+}
+
+fn range_method(name: &str) -> Option<(ParameterList, BuiltinFunction)> {
+    let method: (ParameterList, BuiltinFunction) = match name {
+        "len" => (ParameterList::new(vec!["value".into()]), builtin_len),
+        _ => return None,
+    };
+    Some(method)
+}
+
+/// Calls the method `name` of a builtin type on `receiver`, like `children.push(child)`.
+pub fn call_method(receiver: Object, name: &str, mut arguments: ArgumentList) -> Result<Object, SantaError> {
+    let method = match receiver {
+        Object::String(_) => string_method(name),
+        Object::List(_) => list_method(name),
+        Object::Map(_) => map_method(name),
+        Object::Range(..) => range_method(name),
+        _ => None,
+    };
+
+    let (parameters, function) = method.ok_or_else(|| SantaError::InvalidOperationError {
+        cause: format!("{} has no method {}", receiver.type_name(), name),
+    })?;
+
+    arguments.positional.insert(0, receiver);
+    Function::Builtin(Rc::new(parameters), function).call(arguments)
+}
+
 pub fn get_builtins(scope: &mut Scope) {
-    scope.set_variable("SANTA_VERSION".into(), Object::Integer(unsafe{MANUAL_ID} as i64));
+    scope.set_constant("SANTA_VERSION".into(), Object::Integer(unsafe{MANUAL_ID} as i64));
 
     scope.add_builtin_fn(
         "print",
-        ParameterList::new(vec!["*args".into()]),
+        ParameterList::with_rest(vec![], "args"),
         builtin_print,
     );
 
@@ -137,6 +334,30 @@ pub fn get_builtins(scope: &mut Scope) {
         builtin_len,
     );
 
+    scope.add_builtin_fn(
+        "chars",
+        chars_parameters(),
+        builtin_chars,
+    );
+
+    scope.add_builtin_fn(
+        "bytes",
+        ParameterList::new(vec!["value".into()]),
+        builtin_bytes,
+    );
+
+    scope.add_builtin_fn(
+        "format",
+        ParameterList::with_rest(vec!["template".into()], "args"),
+        builtin_format,
+    );
+
+    scope.add_builtin_fn(
+        "range",
+        ParameterList::with_rest(vec![], "args"),
+        builtin_range,
+    );
+
     scope.add_builtin_fn(
         "assert",
         ParameterList::new(vec!["arg".into()]),
@@ -1,34 +1,248 @@
 use crate::object::Object;
+use crate::span::Span;
+use colored::Colorize;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::error::Error;
+use std::mem::discriminant;
+use std::rc::Rc;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub enum SantaError {
     ParseError { cause: String },
     ParseTreeError { cause: String },
     InvalidOperationError { cause: String },
     IndexOutOfBounds,
+    DivisionByZero,
+    IntegerOverflow,
     KeyError,
     NoDefinitionError,
     DatabaseError {cause: String},
-    ReturnException { value: Object },
     AssertionError,
+    ImportError { cause: String },
+    /// Functions called each other so deep that the stack ran out.
+    RecursionError,
+    /// An error raised by a santa program itself.
+    Raised { kind: String, message: String },
+    /// Wraps an error with the location in the source code it was raised at.
+    Located { span: Span, error: Box<SantaError> },
+    /// Wraps an error that was raised inside a call to the function with this name.
+    InFunction { name: String, error: Box<SantaError> },
+}
+
+impl SantaError {
+    /// Attaches a location to this error, unless it already has a (more precise) one.
+    pub fn located(self, span: &Span) -> Self {
+        match self {
+            Self::Located { .. } => self,
+            error => Self::Located {
+                span: span.clone(),
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// The name of the kind of error, which santa programs can use to tell errors apart.
+    pub fn kind(&self) -> &str {
+        match self {
+            Self::ParseError { .. } => "ParseError",
+            Self::ParseTreeError { .. } => "ParseTreeError",
+            Self::InvalidOperationError { .. } => "InvalidOperationError",
+            Self::IndexOutOfBounds => "IndexOutOfBounds",
+            Self::DivisionByZero => "DivisionByZero",
+            Self::IntegerOverflow => "IntegerOverflow",
+            Self::KeyError => "KeyError",
+            Self::NoDefinitionError => "NoDefinitionError",
+            Self::DatabaseError { .. } => "DatabaseError",
+            Self::AssertionError => "AssertionError",
+            Self::ImportError { .. } => "ImportError",
+            Self::RecursionError => "RecursionError",
+            Self::Raised { kind, .. } => kind,
+            Self::Located { error, .. } | Self::InFunction { error, .. } => error.kind(),
+        }
+    }
+
+    /// The message of the error, without its kind.
+    pub fn message(&self) -> String {
+        match self.without_location() {
+            Self::Raised { message, .. } => message.clone(),
+            error => error.to_string(),
+        }
+    }
+
+    /// Makes the error a santa program raises with `raise value;`. Raising a map with a kind and
+    /// message (like a caught error) keeps those, anything else becomes the message of an `Error`.
+    pub fn raised(value: Object) -> Self {
+        let field = |map: &HashMap<Object, Object>, name: &str| map.get(&Object::String(name.into())).map(|i| i.to_string());
+
+        match value {
+            Object::Map(map) => {
+                let map = map.borrow();
+                Self::Raised {
+                    kind: field(&map, "kind").unwrap_or_else(|| "Error".into()),
+                    message: field(&map, "message").unwrap_or_default(),
+                }
+            }
+            value => Self::Raised {
+                kind: "Error".into(),
+                message: value.to_string(),
+            },
+        }
+    }
+
+    /// Turns the error into the map a santa program gets in a catch block.
+    pub fn to_object(&self) -> Object {
+        let mut map = HashMap::new();
+        map.insert(Object::String("kind".into()), Object::String(self.kind().into()));
+        map.insert(Object::String("message".into()), Object::String(self.message()));
+
+        Object::Map(Rc::new(RefCell::new(map)))
+    }
+
+    /// Marks this error as raised inside a call to the function with this name.
+    pub fn in_function(self, name: &str) -> Self {
+        Self::InFunction {
+            name: name.into(),
+            error: Box::new(self),
+        }
+    }
+
+    /// Yeets back the error without the location and function calls it was raised in.
+    pub fn without_location(&self) -> &Self {
+        match self {
+            Self::Located { error, .. } | Self::InFunction { error, .. } => error.without_location(),
+            error => error,
+        }
+    }
+
+    /// Yeets back the function calls this error passed through, outermost first, together
+    /// with the location in each function that was being evaluated when the error happened.
+    pub fn traceback(&self) -> Vec<(String, Option<&Span>)> {
+        let mut frames = vec![(String::from("<main>"), None)];
+        let mut error = self;
+
+        loop {
+            match error {
+                Self::Located { span, error: inner } => {
+                    let frame = frames.last_mut().expect("there is always a main frame");
+                    // The outermost location is the one in the frame itself
+                    if frame.1.is_none() {
+                        frame.1 = Some(span);
+                    }
+                    error = inner;
+                }
+                Self::InFunction { name, error: inner } => {
+                    frames.push((name.clone(), None));
+                    error = inner;
+                }
+                _ => return frames,
+            }
+        }
+    }
+
+    /// Renders the error for the user, pointing out the offending code if the location is known.
+    pub fn report(&self) -> String {
+        let mut report = format!("{}: {}\n", "error".bright_red().bold(), self);
+        let traceback = self.traceback();
+
+        if let Some((_, Some(span))) = traceback.last() {
+            let (line, column) = span.line_col();
+            let line_text = span.line_text();
+            let gutter = " ".repeat(line.to_string().len());
+
+            // Only underline the part of the span that is on its first line
+            let underlined = span.source.text[span.start..span.end.max(span.start)]
+                .lines()
+                .next()
+                .map(|i| i.chars().count())
+                .unwrap_or(0)
+                .max(1);
+
+            report.push_str(&format!("{}{} {}\n", gutter, "-->".bright_blue(), span));
+            report.push_str(&format!("{} {}\n", gutter, "|".bright_blue()));
+            report.push_str(&format!("{} {} {}\n", line.to_string().bright_blue(), "|".bright_blue(), line_text));
+            report.push_str(&format!(
+                "{} {} {}{}\n",
+                gutter,
+                "|".bright_blue(),
+                " ".repeat(column - 1),
+                "^".repeat(underlined).bright_red()
+            ));
+        }
+
+        if traceback.len() > 1 {
+            report.push_str(&format!("{}\n", "traceback (most recent call last):".bright_blue()));
+
+            // Deep recursion makes the same frame appear many times in a row, which is only shown once
+            let same_frame = |(a, i): &(String, Option<&Span>), (b, j): &(String, Option<&Span>)| {
+                a == b && i.map(|i| (i.start, i.end)) == j.map(|j| (j.start, j.end))
+            };
+            let mut repeated = 0;
+            for (index, (name, span)) in traceback.iter().enumerate() {
+                if index > 0 && same_frame(&traceback[index - 1], &traceback[index]) {
+                    repeated += 1;
+                    continue;
+                }
+                if repeated > 0 {
+                    report.push_str(&format!("  ... repeated {} more times\n", repeated));
+                    repeated = 0;
+                }
+
+                match span {
+                    Some(span) => {
+                        report.push_str(&format!("  in {} at {}\n", name.bold(), span));
+                        report.push_str(&format!("    {}\n", span.line_text().trim()));
+                    }
+                    // Errors raised by builtins have no location in santa code
+                    None => report.push_str(&format!("  in {}\n", name.bold())),
+                }
+            }
+            if repeated > 0 {
+                report.push_str(&format!("  ... repeated {} more times\n", repeated));
+            }
+        }
+
+        report
+    }
 }
 
 impl Error for SantaError {}
 
+/// Errors are equal when they are the same error, no matter where they were raised.
+impl PartialEq for SantaError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.without_location(), other.without_location()) {
+            (Self::ParseError { cause: a }, Self::ParseError { cause: b }) => a == b,
+            (Self::ParseTreeError { cause: a }, Self::ParseTreeError { cause: b }) => a == b,
+            (Self::InvalidOperationError { cause: a }, Self::InvalidOperationError { cause: b }) => a == b,
+            (Self::DatabaseError { cause: a }, Self::DatabaseError { cause: b }) => a == b,
+            (Self::ImportError { cause: a }, Self::ImportError { cause: b }) => a == b,
+            (Self::Raised { kind: a, message: c }, Self::Raised { kind: b, message: d }) => a == b && c == d,
+            // The other errors carry no information
+            (a, b) => discriminant(a) == discriminant(b),
+        }
+    }
+}
+
 impl Display for SantaError {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
         match self {
-            Self::ReturnException {value} => write!(f, "This exception is raised when a fucntion wants to return. The evaluator will never actually raise this error but will instead return it's value. value: {}", value),
             Self::InvalidOperationError {cause} => write!(f, "Operation not supported: {}", cause),
             Self::ParseTreeError {cause} => write!(f, "Error in parse tree construction: {}", cause),
             Self::ParseError {cause} => write!(f, "Parser error: {}", cause),
             Self::IndexOutOfBounds => write!(f, "Index out of bounds"),
+            Self::DivisionByZero => write!(f, "Division by zero"),
+            Self::IntegerOverflow => write!(f, "Integer overflow, the result doesn't fit in 64 bits"),
             Self::KeyError => write!(f, "KeyError, key not found"),
             Self::NoDefinitionError => write!(f, "Variable not defined"),
             Self::DatabaseError {cause} => write!(f, "A database error occured: {}", cause),
             Self::AssertionError => write!(f, "Assertion failed"),
+            Self::ImportError {cause} => write!(f, "Couldn't import module: {}", cause),
+            Self::RecursionError => write!(f, "Recursion too deep, santa ran out of stack space"),
+            Self::Raised {kind, message} => write!(f, "{}: {}", kind, message),
+            Self::Located {error, ..} => write!(f, "{}", error),
+            Self::InFunction {error, ..} => write!(f, "{}", error),
         }
     }
 }
@@ -1,30 +1,105 @@
-use crate::builtins::get_builtins;
+use crate::builtins::{call_method, get_builtins};
 use crate::error::SantaError;
-use crate::function::{ArgumentList, Function, ParameterList};
+use crate::function::{ArgumentList, Function, Parameter, ParameterList};
+use crate::modules::import_module;
 use crate::manual::{increment_manual_id, MANUAL_ID, CONDITIONALS, LOOPS, FUNCTIONS};
-use crate::object::Object;
+use crate::object::{Object, StructType};
 use crate::parser::Operator;
-use crate::parser::{AstNode, BinaryOperator, UnaryOperator};
+use crate::parser::{Accessor, Argument, AstNode, BinaryOperator, FormatPart, MatchArm, Method, Pattern, UnaryOperator};
 use colored::Colorize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::process::exit;
+use std::thread;
+
+/// The size of the stack santa programs run with, deep recursion needs a lot of it.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+/// Calls raise an error when less than this much of the stack is left, instead of overflowing it.
+const STACK_RESERVE: usize = 8 * 1024 * 1024;
+
+thread_local! {
+    /// The address below which calls raise an error, if the stack size of this thread is known.
+    static STACK_LIMIT: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// An address close to the top of the stack. Stacks grow down on every platform santa runs on.
+fn stack_address() -> usize {
+    let marker = 0u8;
+    &marker as *const u8 as usize
+}
+
+/// Runs `run` on a thread with a stack of `STACK_SIZE`, on which too deep recursion raises an error.
+pub fn run_with_stack<T: Send + 'static>(run: impl FnOnce() -> T + Send + 'static) -> T {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            STACK_LIMIT.with(|i| i.set(Some(stack_address() - (STACK_SIZE - STACK_RESERVE))));
+            run()
+        })
+        .expect("Santa's elves couldn't start the interpreter")
+        .join()
+        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+}
+
+/// Raises an error when a call would get too close to the end of the stack, which would crash the interpreter.
+pub fn check_stack() -> Result<(), SantaError> {
+    match STACK_LIMIT.with(|i| i.get()) {
+        Some(limit) if stack_address() < limit => Err(SantaError::RecursionError),
+        _ => Ok(()),
+    }
+}
 
 #[derive(Debug)]
 pub struct Scope {
     parent: Option<Rc<RefCell<Scope>>>,
     locals: HashMap<String, Rc<RefCell<Object>>>,
+    /// Whether this is the top level scope of a program or module, which `global` refers to.
+    global: bool,
+    /// In strict mode, assigning to a variable that was never declared is an error.
+    strict: bool,
+    /// Whether this is the scope a function call runs in. In strict mode, assignments inside
+    /// a function don't reach variables outside of it without `global` or `nonlocal`.
+    function: bool,
+    /// The variables in this scope that can't be assigned to again, like the builtins.
+    constants: HashSet<String>,
 }
 
 impl Scope {
+    /// Makes the global scope of a new program. The builtins live in a parent scope of their own,
+    /// so the global scope only holds what the program defines itself.
     pub fn new() -> Rc<RefCell<Self>> {
-        let mut res = Scope {
+        let mut builtins = Scope {
             parent: None,
             locals: HashMap::new(),
+            global: false,
+            strict: false,
+            function: false,
+            constants: HashSet::new(),
         };
-        get_builtins(&mut res);
+        get_builtins(&mut builtins);
 
-        Rc::new(RefCell::new(res))
+        Rc::new(RefCell::new(Scope {
+            parent: Some(Rc::new(RefCell::new(builtins))),
+            locals: HashMap::new(),
+            global: true,
+            strict: false,
+            function: false,
+            constants: HashSet::new(),
+        }))
+    }
+
+    /// Makes a scope without a parent, which doesn't have the builtins either. It's cheap to make
+    /// for binding arguments that aren't evaluated in it, like the fields of a new struct.
+    pub fn bare() -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Scope {
+            parent: None,
+            locals: HashMap::new(),
+            global: false,
+            strict: false,
+            function: false,
+            constants: HashSet::new(),
+        }))
     }
 
     pub fn add_builtin_fn(
@@ -33,19 +108,42 @@ impl Scope {
         parameters: ParameterList,
         function: fn(Rc<RefCell<Scope>>) -> Result<Object, SantaError>,
     ) {
-        self.set_variable(
+        self.set_constant(
             name.into(),
-            Object::Function(Function::Builtin(parameters, function)),
+            Object::Function(Function::Builtin(Rc::new(parameters), function)),
         );
     }
 
+    /// Binds a variable in this scope that can't be assigned to again.
+    pub fn set_constant(&mut self, name: String, value: Object) {
+        self.constants.insert(name.clone());
+        self.locals.insert(name, Rc::new(RefCell::new(value)));
+    }
+
     pub fn child(me: Rc<RefCell<Self>>) -> Rc<RefCell<Self>> {
+        let strict = me.borrow().strict;
         Rc::new(RefCell::new(Scope {
-            parent: Some(me.clone()),
+            parent: Some(me),
             locals: HashMap::new(),
+            global: false,
+            strict,
+            function: false,
+            constants: HashSet::new(),
         }))
     }
 
+    /// Makes the scope a call of a function defined in `closure` runs in.
+    pub fn function_child(closure: Rc<RefCell<Self>>) -> Rc<RefCell<Self>> {
+        let scope = Self::child(closure);
+        scope.borrow_mut().function = true;
+        scope
+    }
+
+    /// Turns on strict mode for this scope and the scopes made inside it from now on.
+    pub fn set_strict(&mut self) {
+        self.strict = true;
+    }
+
     fn find_variable(&self, name: &String) -> Option<Rc<RefCell<Object>>> {
         if self.locals.contains_key(name) {
             Some(self.locals.get(name)?.clone())
@@ -56,7 +154,35 @@ impl Scope {
         }
     }
 
-    pub fn set_variable(&mut self, name: String, value: Object) {
+    /// Whether the variable `name` refers to from this scope is defined outside of the function this
+    /// scope is in, or `None` if it isn't defined at all.
+    fn is_outside_function(&self, name: &String) -> Option<bool> {
+        if self.locals.contains_key(name) {
+            Some(false)
+        } else if let Some(parent) = &self.parent {
+            parent.borrow().is_outside_function(name).map(|outside| outside || self.function)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the variable `name` refers to from this scope is a constant.
+    fn is_constant(&self, name: &String) -> bool {
+        if self.locals.contains_key(name) {
+            self.constants.contains(name)
+        } else if let Some(parent) = &self.parent {
+            parent.borrow().is_constant(name)
+        } else {
+            false
+        }
+    }
+
+    pub fn set_variable(&mut self, name: String, value: Object) -> Result<(), SantaError> {
+        if self.is_constant(&name) {
+            return Err(SantaError::InvalidOperationError {
+                cause: format!("{} is a constant and can't be assigned to", name),
+            });
+        }
 
         let var = self.find_variable(&name);
         if let Some(var) = var {
@@ -64,6 +190,117 @@ impl Scope {
         } else {
             self.locals.insert(name, Rc::new(RefCell::new(value)));
         }
+
+        Ok(())
+    }
+
+    /// Declares a variable in this scope like `let` and `const` do. Variables in parent scopes can be
+    /// shadowed, but a constant can't be declared again in the same scope.
+    pub fn declare_variable(&mut self, name: String, value: Object, constant: bool) -> Result<(), SantaError> {
+        if self.constants.contains(&name) {
+            return Err(SantaError::InvalidOperationError {
+                cause: format!("{} is a constant and can't be declared again", name),
+            });
+        }
+
+        if constant {
+            self.set_constant(name, value);
+        } else {
+            self.set_local_variable(name, value);
+        }
+
+        Ok(())
+    }
+
+    /// Binds a named function or struct in this scope, shadowing any variable with the same name in
+    /// the parent scopes. Like an assignment, a definition can't replace a constant.
+    pub fn define(&mut self, name: String, value: Object) -> Result<(), SantaError> {
+        if self.is_constant(&name) {
+            return Err(SantaError::InvalidOperationError {
+                cause: format!("{} is a constant and can't be assigned to", name),
+            });
+        }
+
+        self.declare_variable(name, value, false)
+    }
+
+    /// Binds a variable in this scope only, shadowing any variable
+    /// with the same name in the parent scopes.
+    pub fn set_local_variable(&mut self, name: String, value: Object) {
+        self.locals.insert(name, Rc::new(RefCell::new(value)));
+    }
+
+    /// Assigns to a variable like `name = value;` does. In strict mode the variable must have been
+    /// declared before, and inside a function it must be declared in that function, otherwise the
+    /// assignment creates it or changes the variable outside.
+    pub fn assign_variable(&mut self, name: String, value: Object) -> Result<(), SantaError> {
+        if self.strict {
+            match self.is_outside_function(&name) {
+                None => return Err(SantaError::InvalidOperationError {
+                    cause: format!("{} was never declared, use `let {} = ...;` to declare it", name, name),
+                }),
+                // Constants get their own error below
+                Some(true) if !self.is_constant(&name) => return Err(SantaError::InvalidOperationError {
+                    cause: format!("{} belongs outside this function, use `global {}` or `nonlocal {}` to assign to it", name, name, name),
+                }),
+                _ => (),
+            }
+        }
+
+        self.set_variable(name, value)
+    }
+
+    /// Makes `name` in this scope refer to the variable with that name in the global scope,
+    /// which is created if it doesn't exist yet. Constants, like the builtins outside of the
+    /// global scope, are shared as they are and stay constant.
+    pub fn declare_global(&mut self, name: String) {
+        if self.global {
+            return;
+        }
+
+        let mut scope = self.parent.clone().expect("every scope is inside a global scope");
+        while !scope.borrow().global {
+            let parent = scope.borrow().parent.clone().expect("every scope is inside a global scope");
+            scope = parent;
+        }
+
+        if scope.borrow().is_constant(&name) {
+            let variable = scope.borrow().find_variable(&name).expect("constants are always defined");
+            self.constants.insert(name.clone());
+            self.locals.insert(name, variable);
+            return;
+        }
+
+        let mut scope = scope.borrow_mut();
+        let variable = scope
+            .locals
+            .entry(name.clone())
+            .or_insert_with(|| Rc::new(RefCell::new(Object::None)))
+            .clone();
+        self.locals.insert(name, variable);
+    }
+
+    /// Makes `name` in this scope refer to the variable with that name in the closest parent scope that has it.
+    pub fn declare_nonlocal(&mut self, name: String) -> Result<(), SantaError> {
+        let parent = self.parent.clone();
+        let variable = parent
+            .as_ref()
+            .and_then(|i| i.borrow().find_variable(&name))
+            .ok_or_else(|| SantaError::InvalidOperationError {
+                cause: format!("there is no variable {} in a parent scope to use as nonlocal", name),
+            })?;
+
+        // The variable is shared, so it stays constant
+        if parent.is_some_and(|i| i.borrow().is_constant(&name)) {
+            self.constants.insert(name.clone());
+        }
+        self.locals.insert(name, variable);
+        Ok(())
+    }
+
+    /// Yeets back the names of the variables defined in this scope itself.
+    pub fn local_names(&self) -> Vec<String> {
+        self.locals.keys().cloned().collect()
     }
 
     pub fn get_variable(&self, name: &String) -> Option<Object> {
@@ -76,189 +313,771 @@ impl Scope {
         }
     }
 
-    pub fn load_arglist(&mut self, arglist: ArgumentList, paramlist: ParameterList) -> Result<(), SantaError> {
-        let isvararg = if let Some(last) = paramlist.positional.last() {
-          last.starts_with("*")
-        } else {
-            false
-        };
+    /// Binds the arguments of a function call to its parameters in `scope`. Parameters that
+    /// weren't given get their default value, which can use the parameters before them.
+    pub fn load_arglist(scope: &Rc<RefCell<Scope>>, arglist: ArgumentList, paramlist: &ParameterList) -> Result<(), SantaError> {
+        // Defaults can call functions, so this frame is kept small and the matching is done elsewhere
+        let (given, rest, keyword_rest) = Self::match_arguments(arglist, paramlist)?;
 
-        if !isvararg && arglist.positional.len() > paramlist.positional.len() {
-            return Err(SantaError::InvalidOperationError {cause: "Too many arguments for function".into()});
-        } else if !isvararg && arglist.positional.len() < paramlist.positional.len() {
-            return Err(SantaError::InvalidOperationError {cause: "Not enough arguments for function".into()});
-        }else if isvararg && paramlist.positional.len() > 1 && arglist.positional.len() < paramlist.positional.len() -1 {
-            return Err(SantaError::InvalidOperationError {cause: "Not enough arguments for function".into()});
-        }
+        for (parameter, value) in paramlist.positional.iter().zip(given) {
+            let default = parameter.name().and_then(|name| paramlist.defaults.get(name));
+            let value = match (value, default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => flow_to_value(eval_flow(default, scope.clone())?)?,
+                (None, None) => return Err(SantaError::InvalidOperationError {cause: "Not enough arguments for function".into()}),
+            };
 
-        if !isvararg {
-            for (p, arg) in paramlist.positional.iter().zip(arglist.positional.iter()) {
-                if p.starts_with("*") {
-                    return Err(SantaError::InvalidOperationError {cause: "Vararg definition not at the end of function parameterlist".into()});
+            match parameter {
+                Parameter::Pattern(pattern) => {
+                    let mut scope = scope.borrow_mut();
+                    destructure(pattern, value, &mut |name, value| {
+                        scope.set_local_variable(name, value);
+                        Ok(())
+                    })
+                    .map_err(|error| match error {
+                        SantaError::InvalidOperationError {cause} => SantaError::InvalidOperationError {
+                            cause: format!("Couldn't take the argument of parameter {} apart: {}", pattern, cause),
+                        },
+                        error => error,
+                    })?;
                 }
-                self.set_variable(p.clone(), arg.clone());
+                Parameter::Name(name) => scope.borrow_mut().set_local_variable(name.clone(), value),
             }
-        } else {
+        }
+
+        let mut scope = scope.borrow_mut();
+        if let Some(name) = &paramlist.rest {
+            scope.set_local_variable(name.clone(), Object::List(Rc::new(RefCell::new(rest))));
+        }
+        if let Some(name) = &paramlist.keyword_rest {
+            scope.set_local_variable(name.clone(), Object::Map(Rc::new(RefCell::new(keyword_rest))));
+        }
 
-            let (direct, var) = arglist.positional.split_at(paramlist.positional.len()-1);
-            let (params, varargname) = paramlist.positional.split_at(paramlist.positional.len()-1);
+        Ok(())
+    }
 
-            for (p, arg) in params.iter().zip(direct.iter()) {
-                if p.starts_with("*") {
-                    return Err(SantaError::InvalidOperationError {cause: "Multiple variable argument declarations in function signature".into()});
-                }
-                self.set_variable(p.clone(), arg.clone());
+    /// Sorts the arguments of a call into the named parameters they were given for, the extra
+    /// positional arguments and the extra keyword arguments.
+    fn match_arguments(
+        arglist: ArgumentList,
+        paramlist: &ParameterList,
+    ) -> Result<MatchedArguments, SantaError> {
+        let mut given: Vec<Option<Object>> = vec![None; paramlist.positional.len()];
+
+        let mut positional = arglist.positional.into_iter();
+        for (slot, arg) in given.iter_mut().zip(&mut positional) {
+            *slot = Some(arg);
+        }
+
+        let rest: Vec<Object> = positional.collect();
+        if !rest.is_empty() && paramlist.rest.is_none() {
+            return Err(SantaError::InvalidOperationError {cause: "Too many arguments for function".into()});
+        }
+
+        let mut keyword_rest = HashMap::new();
+        for (name, arg) in arglist.keywords {
+            let position = paramlist.positional.iter().position(|parameter| parameter.name() == Some(&name));
+            let duplicate = if let Some(position) = position {
+                given[position].replace(arg).is_some()
+            } else if paramlist.keyword_rest.is_some() {
+                keyword_rest.insert(Object::String(name.clone()), arg).is_some()
+            } else {
+                return Err(SantaError::InvalidOperationError {cause: format!("Unknown keyword argument {}", name)});
+            };
+
+            if duplicate {
+                return Err(SantaError::InvalidOperationError {cause: format!("Got multiple values for argument {}", name)});
             }
+        }
+
+        Ok((given, rest, keyword_rest))
+    }
+}
+
+/// The arguments of a call for each positional parameter, the extra positional ones and the extra keyword ones.
+type MatchedArguments = (Vec<Option<Object>>, Vec<Object>, HashMap<Object, Object>);
+
+/// The way the evaluation of a node or block finished. Besides simply producing
+/// a value, code can yeet a value back out of a function or break out of a loop.
+/// These signals travel up through the evaluator separately from real errors.
+#[derive(Debug, PartialEq)]
+pub enum ControlFlow {
+    Value(Object),
+    Return(Object),
+    Break,
+    Continue,
+}
+
+/// Evaluates an expression to its value. If the expression didn't produce a
+/// value but a control flow signal instead, that signal is yeeted back
+/// from the surrounding function.
+macro_rules! value {
+    ($flow: expr) => {
+        match $flow? {
+            ControlFlow::Value(value) => value,
+            flow => return Ok(flow),
+        }
+    };
+}
+
+/// Evaluates an accessor of an assignment to a `Key`, yeeting back control flow signals like `value!`.
+macro_rules! eval_key {
+    ($accessor: expr, $scope: expr) => {
+        match $accessor {
+            Accessor::Index(index) => Key::Index(value!(eval_flow(index, $scope.clone()))),
+            Accessor::Field(name) => Key::Field(name.clone()),
+        }
+    };
+}
 
-            let mut vararg = vec![];
-            for i in var {
-                vararg.push(i.clone());
+/// Evaluates the arguments of a call, yeeting back control flow signals like `value!`.
+macro_rules! eval_arguments {
+    ($args: expr, $scope: expr) => {{
+        let mut arguments = ArgumentList::new(vec![]);
+        for i in $args {
+            match i {
+                Argument::Positional(arg) => arguments.positional.push(value!(eval_flow(arg, $scope.clone()))),
+                Argument::Keyword(name, arg) => arguments.keywords.push((name.clone(), value!(eval_flow(arg, $scope.clone())))),
+                Argument::Spread(arg) => arguments.positional.extend(value!(eval_flow(arg, $scope.clone())).iterate()?),
+                Argument::KeywordSpread(arg) => match value!(eval_flow(arg, $scope.clone())) {
+                    Object::Map(map) => {
+                        for (key, value) in map.borrow().iter() {
+                            match key {
+                                Object::String(name) => arguments.keywords.push((name.clone(), value.clone())),
+                                _ => return Err(SantaError::InvalidOperationError {
+                                    cause: format!("keyword arguments must have string names, not {}", key),
+                                }),
+                            }
+                        }
+                    }
+                    other => return Err(SantaError::InvalidOperationError {
+                        cause: format!("** can only be used on a map, not {:?}", other),
+                    }),
+                },
             }
+        }
 
-            self.set_variable(varargname[0][1..].into(), Object::List(Rc::new(RefCell::new(vararg))));
+        arguments
+    }};
+}
 
-        };
+/// An evaluated step of the path in an assignment like `a[f()].name += 1;`, so it only has to be
+/// evaluated once.
+enum Key {
+    Index(Object),
+    Field(String),
+}
 
-        Ok(())
+impl Key {
+    fn get(&self, value: &Object) -> Result<Object, SantaError> {
+        match self {
+            Key::Index(index) => value.index(index),
+            Key::Field(name) => value.get_field(name),
+        }
+    }
+
+    fn set(&self, target: &Object, value: &Object) -> Result<(), SantaError> {
+        match self {
+            Key::Index(index) => target.setindex(index, value),
+            Key::Field(name) => target.set_field(name, value),
+        }
     }
 }
 
 pub fn eval_node(node: &AstNode, scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
-    match node {
-        AstNode::None => Ok(Object::None),
-        AstNode::Expression(operatortype) => match operatortype {
-            Operator::Binary { operator, rhs, lhs } => {
-                let rhs_eval = eval_node(rhs, scope.clone())?;
-                let lhs_eval = eval_node(lhs, scope.clone())?;
-                match operator {
-                    BinaryOperator::Add => lhs_eval.add(&rhs_eval),
-                    BinaryOperator::Multiply => lhs_eval.multiply(&rhs_eval),
-                    BinaryOperator::Divide => lhs_eval.divide(&rhs_eval),
-                    BinaryOperator::Subtract => lhs_eval.subtract(&rhs_eval),
-
-                    BinaryOperator::Less => lhs_eval.less(&rhs_eval),
-                    BinaryOperator::Greater => lhs_eval.greater(&rhs_eval),
-                    BinaryOperator::LessEquals => lhs_eval.lessequals(&rhs_eval),
-                    BinaryOperator::GreaterEquals => lhs_eval.greaterequals(&rhs_eval),
-                    BinaryOperator::Equals => lhs_eval.equals(&rhs_eval),
-                    BinaryOperator::NotEquals => lhs_eval.notequals(&rhs_eval),
-
-                    BinaryOperator::Index => lhs_eval.index(&rhs_eval),
-                }
+    flow_to_value(eval_flow(node, scope)?)
+}
+
+/// Converts the control flow signal that reached the top of a function
+/// or a program into the value it yeets back.
+pub fn flow_to_value(flow: ControlFlow) -> Result<Object, SantaError> {
+    match flow {
+        ControlFlow::Value(value) | ControlFlow::Return(value) => Ok(value),
+        ControlFlow::Break => Err(SantaError::InvalidOperationError {
+            cause: "break used outside of a loop".into(),
+        }),
+        ControlFlow::Continue => Err(SantaError::InvalidOperationError {
+            cause: "continue used outside of a loop".into(),
+        }),
+    }
+}
+
+fn expect_boolean(value: Object, operator: &str) -> Result<bool, SantaError> {
+    if let Object::Boolean(value) = value {
+        Ok(value)
+    } else {
+        Err(SantaError::InvalidOperationError {
+            cause: format!("Operand of {} not a boolean.", operator),
+        })
+    }
+}
+
+/// Whether `value` has the shape `pattern` describes and is equal to the literals in it.
+fn pattern_matches(pattern: &Pattern, value: &Object, scope: &Rc<RefCell<Scope>>) -> Result<bool, SantaError> {
+    match (pattern, value) {
+        (Pattern::Name(_), _) | (Pattern::Wildcard, _) => Ok(true),
+        (Pattern::Literal(literal), value) => Ok(eval_node(literal, scope.clone())? == *value),
+        (Pattern::List { before, rest, after }, Object::List(items)) => {
+            let items = items.borrow().clone();
+            let needed = before.len() + after.len();
+            if items.len() < needed || (rest.is_none() && items.len() > needed) {
+                return Ok(false);
             }
-            Operator::Unary { operator, expr } => {
-                let expr_eval = eval_node(expr, scope)?;
 
-                match operator {
-                    UnaryOperator::Negate => expr_eval.negate(),
+            let tail = &items[items.len() - after.len()..];
+            for (pattern, item) in before.iter().zip(&items).chain(after.iter().zip(tail)) {
+                if !pattern_matches(pattern, item, scope)? {
+                    return Ok(false);
                 }
             }
-        },
-        AstNode::Assignment { name, expression, indexes } => {
-            let evaluated = eval_node(expression, scope.clone())?;
-
-            match name.as_ref() {
-                AstNode::Name(name) => {
-                    if indexes.len() > 0 {
-                        let mut curr = scope.borrow().get_variable(name).ok_or(SantaError::NoDefinitionError)?;
-                        for i in indexes.iter().take(indexes.len() - 1) {
-                            let value = eval_node(i.as_ref(), scope.clone())?;
-                            curr = curr.index(&value)?;
-                        }
-                        // We already checked that there was 1 item in the indexes list
-                        curr.setindex(&eval_node(indexes.iter().last().unwrap().as_ref(), scope)?, &evaluated.clone())?;
-                    } else {
-                        scope.borrow_mut().set_variable(name.clone(), evaluated.clone());
-                    }
 
-                    Ok(evaluated)
+            Ok(true)
+        }
+        (Pattern::Map { entries, .. }, Object::Map(map)) => {
+            let map = map.borrow().clone();
+            for (key, pattern) in entries {
+                match map.get(&Object::String(key.clone())) {
+                    Some(item) if pattern_matches(pattern, item, scope)? => (),
+                    _ => return Ok(false),
                 }
-                _ => Err(SantaError::InvalidOperationError {
-                    cause: "Tried to assign to something that's not a variable name".into(),
+            }
+
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Takes `value` apart the way `pattern` describes, handing every name and its part of the value to `bind`.
+/// Literals are not checked here, use `pattern_matches` first for patterns that can contain them.
+fn destructure(pattern: &Pattern, value: Object, bind: &mut dyn FnMut(String, Object) -> Result<(), SantaError>) -> Result<(), SantaError> {
+    match pattern {
+        Pattern::Name(name) => bind(name.clone(), value),
+        Pattern::Wildcard | Pattern::Literal(_) => Ok(()),
+        Pattern::List { before, rest, after } => {
+            let mut items: Vec<Object> = value.iterate()?.collect();
+            let needed = before.len() + after.len();
+
+            if items.len() < needed || (rest.is_none() && items.len() > needed) {
+                return Err(SantaError::InvalidOperationError {
+                    cause: format!(
+                        "Expected {}{} values to unpack, got {}",
+                        if rest.is_some() { "at least " } else { "" },
+                        needed,
+                        items.len()
+                    ),
+                });
+            }
+
+            let tail = items.split_off(items.len() - after.len());
+            let middle = items.split_off(before.len());
+
+            for (pattern, item) in before.iter().zip(items) {
+                destructure(pattern, item, bind)?;
+            }
+            if let Some(rest) = rest {
+                destructure(rest, Object::List(Rc::new(RefCell::new(middle))), bind)?;
+            }
+            for (pattern, item) in after.iter().zip(tail) {
+                destructure(pattern, item, bind)?;
+            }
+
+            Ok(())
+        }
+        Pattern::Map { entries, rest } => {
+            let mut remaining = match value {
+                Object::Map(map) => map.borrow().clone(),
+                other => return Err(SantaError::InvalidOperationError {
+                    cause: format!("Only maps can be taken apart with {{...}}, not {:?}", other),
                 }),
+            };
+
+            for (key, pattern) in entries {
+                let item = remaining.remove(&Object::String(key.clone())).ok_or(SantaError::KeyError)?;
+                destructure(pattern, item, bind)?;
+            }
+            if let Some(rest) = rest {
+                destructure(rest, Object::Map(Rc::new(RefCell::new(remaining))), bind)?;
             }
+
+            Ok(())
         }
+    }
+}
 
-        AstNode::List(list) => Ok(Object::List( Rc::new(RefCell::new(list.iter()
-            .map(|i| eval_node(i, scope.clone()))
-            .collect::<Result<Vec<Object>, SantaError>>()?)))),
+/// Applies an operator to two values. Structs can overload the operators with methods like `__add__`,
+/// which the methods of `Object` call.
+fn apply_binary_operator(operator: &BinaryOperator, lhs: &Object, rhs: &Object) -> Result<Object, SantaError> {
+    match operator {
+        BinaryOperator::Add => lhs.add(rhs),
+        BinaryOperator::Multiply => lhs.multiply(rhs),
+        BinaryOperator::Divide => lhs.divide(rhs),
+        BinaryOperator::Subtract => lhs.subtract(rhs),
+        BinaryOperator::FloorDivide => lhs.floordivide(rhs),
+        BinaryOperator::Modulo => lhs.modulo(rhs),
+        BinaryOperator::Power => lhs.power(rhs),
 
-        AstNode::Map(map) => Ok(Object::Map( Rc::new(RefCell::new(map.iter()
-            .map(|i| Ok((eval_node(i.0.as_ref(), scope.clone())?, eval_node(i.1.as_ref(), scope.clone())?)))
-            .collect::<Result<HashMap<Object, Object>, SantaError>>()?)))),
+        BinaryOperator::BitAnd => lhs.bitand(rhs),
+        BinaryOperator::BitOr => lhs.bitor(rhs),
+        BinaryOperator::BitXor => lhs.bitxor(rhs),
+        BinaryOperator::ShiftLeft => lhs.shiftleft(rhs),
+        BinaryOperator::ShiftRight => lhs.shiftright(rhs),
 
-        AstNode::Integer(integer) => Ok(Object::Integer(integer.clone())),
-        AstNode::Boolean(boolean) => Ok(Object::Boolean(boolean.clone())),
-        AstNode::Float(float) => Ok(Object::Float(float.clone())),
-        AstNode::String(string) => Ok(Object::String(string.clone())),
-        AstNode::Name(string) => {
-            Ok(scope.borrow()
-                .get_variable(string)
-                .ok_or(SantaError::NoDefinitionError)?)
+        BinaryOperator::Less => lhs.less(rhs),
+        BinaryOperator::Greater => lhs.greater(rhs),
+        BinaryOperator::LessEquals => lhs.lessequals(rhs),
+        BinaryOperator::GreaterEquals => lhs.greaterequals(rhs),
+        BinaryOperator::Equals => lhs.equals(rhs),
+        BinaryOperator::NotEquals => lhs.notequals(rhs),
+
+        BinaryOperator::And | BinaryOperator::Or => unreachable!("evaluated with short-circuiting"),
+
+        BinaryOperator::Index => lhs.index(rhs),
+    }
+}
+
+// Calls and the bigger statements are evaluated outside of `eval_unspanned`, so the space they take
+// on the stack isn't taken by every level of recursion.
+
+fn eval_functioncall(value: &AstNode, args: &[Argument], scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    let function = value!(eval_flow(value, scope.clone()));
+    let arguments = eval_arguments!(args, scope);
+
+    Ok(ControlFlow::Value(function.call(arguments)?))
+}
+
+fn eval_methodcall(value: &AstNode, name: &str, args: &[Argument], scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    let receiver = value!(eval_flow(value, scope.clone()));
+    let mut arguments = eval_arguments!(args, scope);
+
+    Ok(ControlFlow::Value(match receiver {
+        // A field of a struct can hold a function, which is called without the struct itself
+        Object::Struct(ref structtype, _) if structtype.fields.iter().any(|i| i == name) => receiver.get_field(name)?.call(arguments)?,
+        Object::Struct(ref structtype, _) => {
+            let method = structtype.methods.get(name).cloned().ok_or_else(|| SantaError::InvalidOperationError {
+                cause: format!("{} has no field or method {}", structtype.name, name),
+            })?;
+            arguments.positional.insert(0, receiver.clone());
+            method.call(arguments)?
         }
-        AstNode::Functioncall { value, args } => {
-            let variable = eval_node(value, scope.clone())?;
-            let mut arguments = ArgumentList::new(vec![]);
-            for i in args {
-                arguments.positional.push(eval_node(i, scope.clone())?)
+        receiver => call_method(receiver, name, arguments)?,
+    }))
+}
+
+fn eval_operator(operator: &Operator, scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    Ok(ControlFlow::Value(match operator {
+        // `and` and `or` only evaluate their right hand side when it decides the result.
+        Operator::Binary { operator: BinaryOperator::And, rhs, lhs } => {
+            if expect_boolean(value!(eval_flow(lhs, scope.clone())), "and")? {
+                Object::Boolean(expect_boolean(value!(eval_flow(rhs, scope)), "and")?)
+            } else {
+                Object::Boolean(false)
             }
+        }
+        Operator::Binary { operator: BinaryOperator::Or, rhs, lhs } => {
+            if expect_boolean(value!(eval_flow(lhs, scope.clone())), "or")? {
+                Object::Boolean(true)
+            } else {
+                Object::Boolean(expect_boolean(value!(eval_flow(rhs, scope)), "or")?)
+            }
+        }
+        Operator::Binary { operator, rhs, lhs } => {
+            let rhs_eval = value!(eval_flow(rhs, scope.clone()));
+            let lhs_eval = value!(eval_flow(lhs, scope.clone()));
+            apply_binary_operator(operator, &lhs_eval, &rhs_eval)?
+        }
+        Operator::Unary { operator, expr } => {
+            let expr_eval = value!(eval_flow(expr, scope));
 
-            variable.call(arguments)
+            match operator {
+                UnaryOperator::Negate => expr_eval.negate(),
+                UnaryOperator::Not => expr_eval.not(),
+            }?
         }
-        AstNode::Function {
-            name,
-            parameterlist,
-            code,
-        } => {
-            let func = Object::Function(Function::User(parameterlist.clone(), scope.clone(), code.clone()));
-
-
-            // If you gave the function a name, assign it to a variable with that name.
-            if let AstNode::Name(name) = *name.clone() {
-                if unsafe {MANUAL_ID} == FUNCTIONS && &name == "assert_eq" {
-                    println!("{}", "Found a function called assert_eq. testing!".yellow());
-                    let noteq = func.call(
-                        ArgumentList::new(vec![Object::Integer(1), Object::Integer(2)])
-                    );
-                    let eq = func.call(
-                        ArgumentList::new(vec![Object::Integer(1), Object::Integer(1)])
-                    );
-
-                    if noteq == Err(SantaError::AssertionError) && eq == Ok(Object::Integer(42)){
-                        println!(
-                            "{}",
-                            "You found the right answer to Test 4!".yellow()
-                        );
-                        increment_manual_id();
-                    }
+    }))
+}
+
+fn eval_assignment(name: &AstNode, expression: &AstNode, indexes: &[Accessor], scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    let evaluated = value!(eval_flow(expression, scope.clone()));
+
+    match name {
+        AstNode::Name(name) => {
+            if let Some((last, rest)) = indexes.split_last() {
+                let mut curr = scope.borrow().get_variable(name).ok_or(SantaError::NoDefinitionError)?;
+                for i in rest {
+                    let key = eval_key!(i, scope);
+                    curr = key.get(&curr)?;
                 }
+                eval_key!(last, scope).set(&curr, &evaluated)?;
+            } else {
+                scope.borrow_mut().assign_variable(name.clone(), evaluated.clone())?;
+            }
+        }
+        AstNode::Pattern(pattern) => {
+            let mut scope = scope.borrow_mut();
+            destructure(pattern, evaluated.clone(), &mut |name, value| scope.assign_variable(name, value))?;
+        }
+        _ => return Err(SantaError::InvalidOperationError {
+            cause: "Tried to assign to something that's not a variable name".into(),
+        }),
+    }
+
+    Ok(ControlFlow::Value(evaluated))
+}
+
+fn eval_compound_assignment(
+    name: &AstNode,
+    operator: &BinaryOperator,
+    expression: &AstNode,
+    indexes: &[Accessor],
+    scope: Rc<RefCell<Scope>>,
+) -> Result<ControlFlow, SantaError> {
+    let name = match name {
+        AstNode::Name(name) => name,
+        _ => return Err(SantaError::InvalidOperationError {
+            cause: "Tried to assign to something that's not a variable name".into(),
+        }),
+    };
+
+    let new = if let Some((last, rest)) = indexes.split_last() {
+        let mut curr = scope.borrow().get_variable(name).ok_or(SantaError::NoDefinitionError)?;
+        for i in rest {
+            let key = eval_key!(i, scope);
+            curr = key.get(&curr)?;
+        }
+        let key = eval_key!(last, scope);
+        let old = key.get(&curr)?;
+        let evaluated = value!(eval_flow(expression, scope));
+        let new = apply_binary_operator(operator, &old, &evaluated)?;
+        key.set(&curr, &new)?;
+        new
+    } else {
+        let old = scope.borrow().get_variable(name).ok_or(SantaError::NoDefinitionError)?;
+        let evaluated = value!(eval_flow(expression, scope.clone()));
+        let new = apply_binary_operator(operator, &old, &evaluated)?;
+        scope.borrow_mut().assign_variable(name.clone(), new.clone())?;
+        new
+    };
+
+    Ok(ControlFlow::Value(new))
+}
 
-                scope.borrow_mut().set_variable(name, func.clone());
+/// Binds the value of an iteration of a for loop to its variable, or to the names in its pattern.
+fn bind_loop_variable(variable: &AstNode, value: Object, subscope: &Rc<RefCell<Scope>>) -> Result<(), SantaError> {
+    let mut subscope = subscope.borrow_mut();
+    match variable {
+        AstNode::Name(name) => subscope.set_local_variable(name.clone(), value),
+        AstNode::Pattern(pattern) => destructure(pattern, value, &mut |name, value| {
+            subscope.set_local_variable(name, value);
+            Ok(())
+        })?,
+        _ => return Err(SantaError::InvalidOperationError {
+            cause: "Tried to loop with something that's not a variable name".into(),
+        }),
+    }
+
+    Ok(())
+}
+
+/// Runs the block of the first arm whose pattern matches the value and whose guard holds.
+fn eval_match(value: &AstNode, arms: &[MatchArm], scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    let value = value!(eval_flow(value, scope.clone()));
+
+    for MatchArm { pattern, guard, code } in arms {
+        if !pattern_matches(pattern, &value, &scope)? {
+            continue;
+        }
+
+        let subscope = Scope::child(scope.clone());
+        destructure(pattern, value.clone(), &mut |name, value| {
+            subscope.borrow_mut().set_local_variable(name, value);
+            Ok(())
+        })?;
+
+        if let Some(guard) = guard {
+            if !expect_boolean(value!(eval_flow(guard, subscope.clone())), "match guard")? {
+                continue;
+            }
+        }
+
+        return eval_block_with_scope(code, subscope);
+    }
+
+    // Just like an if statement without an else block
+    Ok(ControlFlow::Value(Object::None))
+}
+
+fn eval_while(condition: &AstNode, code: &Vec<Box<AstNode>>, scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    if unsafe { MANUAL_ID } == LOOPS {
+        println!("{}", "You used a while loop for the first time!".yellow());
+        increment_manual_id();
+    }
+
+    let mut value = value!(eval_flow(condition, scope.clone()));
+
+    if let Object::Boolean(_) = value {
+        while let Object::Boolean(true) = value {
+            let subscope = Scope::child(scope.clone());
+            match eval_block_with_scope(code, subscope)? {
+                ControlFlow::Break => break,
+                ControlFlow::Return(value) => return Ok(ControlFlow::Return(value)),
+                ControlFlow::Value(_) | ControlFlow::Continue => (),
             }
+            value = value!(eval_flow(condition, scope.clone()));
+        }
+        Ok(ControlFlow::Value(Object::None))
+    } else {
+        Err(SantaError::InvalidOperationError {
+            cause: "Expresion in if statement not a boolean.".into(),
+        })
+    }
+}
+
+fn eval_for(variable: &AstNode, iterable: &AstNode, code: &Vec<Box<AstNode>>, scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    for value in value!(eval_flow(iterable, scope.clone())).iterate()? {
+        let subscope = Scope::child(scope.clone());
+        bind_loop_variable(variable, value, &subscope)?;
+        match eval_block_with_scope(code, subscope)? {
+            ControlFlow::Break => break,
+            ControlFlow::Return(value) => return Ok(ControlFlow::Return(value)),
+            ControlFlow::Value(_) | ControlFlow::Continue => (),
+        }
+    }
+
+    Ok(ControlFlow::Value(Object::None))
+}
+
+fn eval_slice(bounds: [&Option<Box<AstNode>>; 3], scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    let mut evaluated = vec![];
+    for bound in &bounds {
+        evaluated.push(match bound {
+            Some(bound) => match value!(eval_flow(bound, scope.clone())) {
+                Object::Integer(i) => Some(i),
+                Object::None => None,
+                other => return Err(SantaError::InvalidOperationError {
+                    cause: format!("slice bounds must be integers, not {:?}", other),
+                }),
+            },
+            None => None,
+        });
+    }
+
+    Ok(ControlFlow::Value(Object::Slice(evaluated[0], evaluated[1], evaluated[2])))
+}
+
+/// Declares a variable like `let` does, or a constant like `const` does.
+fn eval_declaration(name: &AstNode, expression: &AstNode, constant: bool, scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    let evaluated = value!(eval_flow(expression, scope.clone()));
+
+    match name {
+        AstNode::Name(name) => scope.borrow_mut().declare_variable(name.clone(), evaluated.clone(), constant)?,
+        _ => return Err(SantaError::InvalidOperationError {
+            cause: "Tried to declare something that's not a variable name".into(),
+        }),
+    }
+
+    Ok(ControlFlow::Value(evaluated))
+}
+
+fn eval_list(list: &[Box<AstNode>], scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    let mut values = vec![];
+    for i in list {
+        values.push(value!(eval_flow(i, scope.clone())));
+    }
+    Ok(ControlFlow::Value(Object::List(Rc::new(RefCell::new(values)))))
+}
+
+fn eval_map(map: &[(Box<AstNode>, Box<AstNode>)], scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    let mut values = HashMap::new();
+    for (key, value) in map {
+        let key = value!(eval_flow(key.as_ref(), scope.clone()));
+        key.check_hashable()?;
+        values.insert(key, value!(eval_flow(value.as_ref(), scope.clone())));
+    }
+    Ok(ControlFlow::Value(Object::Map(Rc::new(RefCell::new(values)))))
+}
 
-            Ok(func)
+fn eval_format_string(parts: &[FormatPart], scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    let mut result = String::new();
+    for part in parts {
+        match part {
+            FormatPart::Text(text) => result.push_str(text),
+            FormatPart::Value(value, spec) => result.push_str(&spec.apply(&value!(eval_flow(value, scope.clone())))),
         }
-        AstNode::WhileLoop { condition, code } => {
-            if unsafe { MANUAL_ID } == LOOPS {
-                println!("{}", "You used a while loop for the first time!".yellow());
+    }
+    Ok(ControlFlow::Value(Object::String(result)))
+}
+
+/// Defines a struct, binding its constructor to the name of the struct.
+fn eval_struct(
+    name: &str,
+    fields: &[String],
+    methods: &[Method],
+    scope: Rc<RefCell<Scope>>,
+) -> Result<ControlFlow, SantaError> {
+    let methods = methods
+        .iter()
+        .map(|(method, parameterlist, code)| {
+            let function_name = Some(format!("{}.{}", name, method));
+            (method.clone(), Function::User(parameterlist.clone(), scope.clone(), code.clone(), function_name))
+        })
+        .collect();
+
+    let constructor = Object::Function(Function::Constructor(Rc::new(StructType {
+        name: name.into(),
+        fields: fields.to_vec(),
+        methods,
+    })));
+    scope.borrow_mut().define(name.into(), constructor.clone())?;
+
+    Ok(ControlFlow::Value(constructor))
+}
+
+/// Makes a function that closes over `scope`, binding it to its name if it has one.
+fn eval_function(
+    name: &AstNode,
+    parameterlist: &Rc<ParameterList>,
+    code: &[Box<AstNode>],
+    scope: Rc<RefCell<Scope>>,
+) -> Result<ControlFlow, SantaError> {
+    let function_name = match name {
+        AstNode::Name(name) => Some(name.clone()),
+        _ => None,
+    };
+    let func = Object::Function(Function::User(parameterlist.clone(), scope.clone(), code.to_vec(), function_name));
+
+    // If you gave the function a name, assign it to a variable with that name.
+    if let AstNode::Name(name) = name.clone() {
+        if unsafe {MANUAL_ID} == FUNCTIONS && &name == "assert_eq" {
+            println!("{}", "Found a function called assert_eq. testing!".yellow());
+            let noteq = func.call(
+                ArgumentList::new(vec![Object::Integer(1), Object::Integer(2)])
+            );
+            let eq = func.call(
+                ArgumentList::new(vec![Object::Integer(1), Object::Integer(1)])
+            );
+
+            if noteq == Err(SantaError::AssertionError) && eq == Ok(Object::Integer(42)){
+                println!(
+                    "{}",
+                    "You found the right answer to Test 4!".yellow()
+                );
                 increment_manual_id();
             }
+        }
 
-            let mut value = eval_node(condition.as_ref(), scope.clone())?;
+        scope.borrow_mut().define(name, func.clone())?;
+    }
 
-            if let Object::Boolean(_) = value {
-                while let Object::Boolean(true) = value {
-                    let subscope = Scope::child(scope.clone());
-                    eval_block_with_scope(&code, subscope)?;
-                    value = eval_node(condition.as_ref(), scope.clone())?;
-                }
-                Ok(Object::None)
-            } else {
-                Err(SantaError::InvalidOperationError {
-                    cause: "Expresion in if statement not a boolean.".into(),
-                })
-            }
+    Ok(ControlFlow::Value(func))
+}
+
+/// Runs a try block, handing the error it raises to the catch block if there is one.
+fn eval_try(
+    code: &Vec<Box<AstNode>>,
+    errorname: Option<&AstNode>,
+    catchcode: Option<&Vec<Box<AstNode>>>,
+    finallycode: Option<&Vec<Box<AstNode>>>,
+    scope: Rc<RefCell<Scope>>,
+) -> Result<ControlFlow, SantaError> {
+    let result = match (eval_block_with_scope(code, Scope::child(scope.clone())), errorname, catchcode) {
+        (Err(e), Some(errorname), Some(catchcode)) => {
+            let name = match errorname {
+                AstNode::Name(name) => name.clone(),
+                _ => return Err(SantaError::InvalidOperationError {
+                    cause: "The error in a catch block can only be assigned to a name".into(),
+                }),
+            };
+
+            let subscope = Scope::child(scope.clone());
+            subscope.borrow_mut().set_local_variable(name, e.to_object());
+            eval_block_with_scope(catchcode, subscope)
+        }
+        (result, _, _) => result,
+    };
+
+    // The finally block always runs, and if it yeets back, breaks or
+    // continues itself that wins over whatever happened before.
+    if let Some(finallycode) = finallycode {
+        match eval_block_with_scope(finallycode, Scope::child(scope))? {
+            ControlFlow::Value(_) => (),
+            flow => return Ok(flow),
+        }
+    }
+
+    result
+}
+
+/// Binds the names a module defines in the importing scope, or only the given ones.
+fn eval_import(path: &str, names: Option<&[String]>, scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    let module = import_module(path)?;
+    let module = module.borrow();
+
+    let names = match names {
+        Some(names) => names.to_vec(),
+        None => module.local_names(),
+    };
+
+    for name in names {
+        let value = module.get_variable(&name).ok_or_else(|| SantaError::ImportError {
+            cause: format!("{} is not defined in {}", name, path),
+        })?;
+        // Imported names belong to the importing scope, just like they were declared there, and
+        // constants of the module stay constant
+        let constant = module.is_constant(&name);
+        scope.borrow_mut().declare_variable(name, value, constant)?;
+    }
+
+    Ok(ControlFlow::Value(Object::None))
+}
+
+/// Evaluates a node, attaching the location it was parsed from to the errors it raises. The spans
+/// are taken off here, so they don't cost a frame of the (large) `eval_unspanned` on the stack each.
+pub fn eval_flow(node: &AstNode, scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    // The innermost span is the most precise location
+    let mut node = node;
+    let mut location = None;
+    while let AstNode::Spanned(span, inner) = node {
+        location = Some(span);
+        node = inner;
+    }
+
+    match location {
+        Some(span) => eval_unspanned(node, scope).map_err(|e| e.located(span)),
+        None => eval_unspanned(node, scope),
+    }
+}
+
+fn eval_unspanned(node: &AstNode, scope: Rc<RefCell<Scope>>) -> Result<ControlFlow, SantaError> {
+    Ok(ControlFlow::Value(match node {
+        AstNode::None => Object::None,
+        AstNode::Expression(operator) => return eval_operator(operator, scope),
+        AstNode::Assignment { name, expression, indexes } => return eval_assignment(name, expression, indexes, scope),
+        // The index path is only evaluated once, so `a[f()] += 1;` calls `f` a single time.
+        AstNode::CompoundAssignment { name, operator, expression, indexes } => {
+            return eval_compound_assignment(name, operator, expression, indexes, scope)
+        }
+
+        AstNode::List(list) => return eval_list(list, scope),
+        AstNode::Map(map) => return eval_map(map, scope),
+
+        AstNode::Integer(integer) => Object::Integer(*integer),
+        AstNode::Boolean(boolean) => Object::Boolean(*boolean),
+        AstNode::Float(float) => Object::Float(*float),
+        AstNode::String(string) => Object::String(string.clone()),
+        AstNode::FormatString(parts) => return eval_format_string(parts, scope),
+        AstNode::Name(string) => {
+            scope.borrow()
+                .get_variable(string)
+                .ok_or(SantaError::NoDefinitionError)?
         }
+        AstNode::Functioncall { value, args } => return eval_functioncall(value, args, scope),
+        AstNode::MethodCall { value, name, args } => return eval_methodcall(value, name, args, scope),
+        AstNode::Field { value, name } => value!(eval_flow(value, scope)).get_field(name)?,
+        AstNode::Struct { name, fields, methods } => return eval_struct(name, fields, methods, scope),
+        AstNode::Function { name, parameterlist, code } => return eval_function(name, parameterlist, code, scope),
+        AstNode::WhileLoop { condition, code } => return eval_while(condition, code, scope),
+        AstNode::ForLoop { variable, iterable, code } => return eval_for(variable, iterable, code, scope),
         AstNode::IfStatement {
             condition,
             code,
@@ -272,90 +1091,95 @@ pub fn eval_node(node: &AstNode, scope: Rc<RefCell<Scope>>) -> Result<Object, Sa
                 increment_manual_id();
             }
 
-            if let Object::Boolean(value) = eval_node(condition, scope.clone())? {
+            if let Object::Boolean(value) = value!(eval_flow(condition, scope.clone())) {
                 let subscope = Scope::child(scope);
                 if value {
-                    eval_block_with_scope(code.as_ref(), subscope)
+                    return eval_block_with_scope(code, subscope);
                 } else if let Some(elsecode) = elsecode{
-                    eval_block_with_scope(elsecode.as_ref(), subscope)
+                    return eval_block_with_scope(elsecode, subscope);
                 } else {
-                    Ok(Object::None)
+                    Object::None
                 }
             } else {
-                Err(SantaError::InvalidOperationError {
+                return Err(SantaError::InvalidOperationError {
                     cause: "Expresion in if statement not a boolean.".into(),
                 })
             }
         }
-        AstNode::Return(expr) => Err(SantaError::ReturnException {
-            value: eval_node(expr, scope)?,
+        AstNode::Match { value, arms } => return eval_match(value, arms, scope),
+        AstNode::TryStatement { code, errorname, catchcode, finallycode } => {
+            return eval_try(code, errorname.as_deref(), catchcode.as_ref(), finallycode.as_ref(), scope)
+        }
+        AstNode::Slice { start, end, step } => return eval_slice([start, end, step], scope),
+        AstNode::Let { name, expression } => return eval_declaration(name, expression, false, scope),
+        AstNode::Const { name, expression } => return eval_declaration(name, expression, true, scope),
+        AstNode::Global(names) => {
+            for name in names {
+                scope.borrow_mut().declare_global(name.clone());
+            }
+
+            Object::None
+        }
+        AstNode::Nonlocal(names) => {
+            for name in names {
+                scope.borrow_mut().declare_nonlocal(name.clone())?;
+            }
+
+            Object::None
+        }
+        AstNode::Strict => {
+            scope.borrow_mut().set_strict();
+            Object::None
+        }
+        AstNode::Import { path, names } => return eval_import(path, names.as_deref(), scope),
+        AstNode::Pattern(_) => return Err(SantaError::InvalidOperationError {
+            cause: "A pattern can only be assigned to".into(),
         }),
-    }
+        AstNode::Raise(expr) => return Err(SantaError::raised(value!(eval_flow(expr, scope)))),
+        AstNode::Return(expr) => return Ok(ControlFlow::Return(value!(eval_flow(expr, scope)))),
+        AstNode::Break => return Ok(ControlFlow::Break),
+        AstNode::Continue => return Ok(ControlFlow::Continue),
+        AstNode::Spanned(..) => unreachable!("eval_flow takes the spans off"),
+    }))
 }
 
 pub fn eval(ast: Vec<Box<AstNode>>) {
     let scope = Scope::new();
 
-    eval_with_scope(ast, scope);
+    if let Err(e) = eval_with_scope_err(ast, scope) {
+        eprint!("{}", e.report());
+        exit(1);
+    }
 }
 
+/// Evaluates the statements of a block one by one. Evaluation stops at the first statement that
+/// yeets back a control flow signal other than a value, and that signal is passed on to the caller.
 pub fn eval_block_with_scope(
     ast: &Vec<Box<AstNode>>,
     scope: Rc<RefCell<Scope>>,
-) -> Result<Object, SantaError> {
+) -> Result<ControlFlow, SantaError> {
     let mut last_answer = Object::None;
     for node in ast {
-        match eval_node(node.as_ref(), scope.clone()) {
-            Err(SantaError::ReturnException { value }) => {
-                return Ok(value);
-            }
-            Err(e) => {
-                return Err(e);
-            }
-            Ok(i) => {
+        match eval_flow(node.as_ref(), scope.clone())? {
+            ControlFlow::Value(i) => {
                 last_answer = i;
             }
+            flow => {
+                return Ok(flow);
+            }
         };
     }
 
-    Ok(last_answer)
+    Ok(ControlFlow::Value(last_answer))
 }
 
+#[allow(dead_code)]
 pub fn eval_with_scope(ast: Vec<Box<AstNode>>, scope: Rc<RefCell<Scope>>) -> Object {
-    let mut last_answer = Object::None;
-    for node in ast {
-        match eval_node(node.as_ref(), scope.clone()) {
-            Err(SantaError::ReturnException { value }) => {
-                return value;
-            }
-            Err(e) => {
-                Err(e).unwrap()
-            }
-            Ok(i) => {
-                last_answer = i;
-            }
-        };
-    }
-
-    last_answer
+    eval_with_scope_err(ast, scope).unwrap()
 }
 
 
 
 pub fn eval_with_scope_err(ast: Vec<Box<AstNode>>, scope: Rc<RefCell<Scope>>) -> Result<Object, SantaError> {
-    let mut last_answer = Object::None;
-    for node in ast {
-        match eval_node(node.as_ref(), scope.clone()) {
-            Err(SantaError::ReturnException { value }) => {
-                return Ok(value);
-            }
-            Err(e) => {
-                return Err(e);
-            }
-            Ok(i) => {
-                last_answer = i;
-            }
-        };
-    }
-    Ok(last_answer)
-}
\ No newline at end of file
+    flow_to_value(eval_block_with_scope(&ast, scope)?)
+}
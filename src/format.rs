@@ -0,0 +1,175 @@
+use crate::error::SantaError;
+use crate::object::Object;
+
+/// The largest width or precision a value can be formatted with, so a typo can't make santa
+/// run out of memory.
+pub const MAX_WIDTH: usize = 1000;
+
+/// Where a value goes when it is shorter than the width it is formatted to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// How to format a value, written like `>8.2` after the `:` in `{value:>8.2}`. It consists of
+/// an optional fill character and alignment (`<`, `>` or `^`), a width and a precision.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormatSpec {
+    pub fill: char,
+    pub align: Option<Align>,
+    pub width: Option<usize>,
+    /// The number of decimals of a number, or the maximum number of characters of anything else.
+    pub precision: Option<usize>,
+}
+
+impl Default for FormatSpec {
+    fn default() -> Self {
+        Self {
+            fill: ' ',
+            align: None,
+            width: None,
+            precision: None,
+        }
+    }
+}
+
+impl FormatSpec {
+    /// Parses a format specification, yeeting back why it isn't a valid one if it isn't.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid format specification {}", spec);
+        let align_of = |c: char| match c {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            _ => None,
+        };
+        let number = |digits: &[char]| {
+            let digits: String = digits.iter().collect();
+            match digits.parse::<usize>() {
+                Ok(number) if number <= MAX_WIDTH => Ok(number),
+                _ => Err(format!("Format width or precision {} is too large, it can be at most {}", digits, MAX_WIDTH)),
+            }
+        };
+        let digits = |rest: &[char]| rest.iter().take_while(|c| c.is_ascii_digit()).count();
+
+        let chars: Vec<char> = spec.chars().collect();
+        let mut result = Self::default();
+        let mut rest = &chars[..];
+
+        match rest {
+            [fill, align, ..] if align_of(*align).is_some() => {
+                result.fill = *fill;
+                result.align = align_of(*align);
+                rest = &rest[2..];
+            }
+            [align, ..] if align_of(*align).is_some() => {
+                result.align = align_of(*align);
+                rest = &rest[1..];
+            }
+            _ => (),
+        }
+
+        let width = digits(rest);
+        if width > 0 {
+            result.width = Some(number(&rest[..width])?);
+            rest = &rest[width..];
+        }
+
+        if let ['.', after @ ..] = rest {
+            let precision = digits(after);
+            if precision == 0 {
+                return Err(invalid());
+            }
+            result.precision = Some(number(&after[..precision])?);
+            rest = &after[precision..];
+        }
+
+        if rest.is_empty() {
+            Ok(result)
+        } else {
+            Err(invalid())
+        }
+    }
+
+    pub fn apply(&self, value: &Object) -> String {
+        let text = match (value, self.precision) {
+            (Object::Float(f), Some(precision)) => format!("{:.*}", precision, f),
+            (Object::Integer(i), Some(0)) => i.to_string(),
+            (Object::Integer(i), Some(precision)) => format!("{}.{}", i, "0".repeat(precision)),
+            (value, Some(precision)) => value.to_string().chars().take(precision).collect(),
+            (value, None) => value.to_string(),
+        };
+
+        // Numbers line up on the right by default, so they can be put in columns
+        let align = self.align.unwrap_or(match value {
+            Object::Integer(_) | Object::Float(_) => Align::Right,
+            _ => Align::Left,
+        });
+
+        let padding = self.width.unwrap_or(0).saturating_sub(text.chars().count());
+        let (before, after) = match align {
+            Align::Left => (0, padding),
+            Align::Right => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+
+        let fill = |n: usize| self.fill.to_string().repeat(n);
+        format!("{}{}{}", fill(before), text, fill(after))
+    }
+}
+
+/// Fills in the `{}` placeholders in `template` with `args`, in order. A placeholder can also pick an
+/// argument by position like `{1}` and have a format specification like `{:>8}`. `{{` and `}}` stand
+/// for a single brace.
+pub fn format_template(template: &str, args: &[Object]) -> Result<String, SantaError> {
+    let invalid = |cause: String| SantaError::InvalidOperationError { cause };
+
+    let mut result = String::new();
+    let mut next_argument = 0;
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(invalid("Unclosed { in format string".into())),
+                    }
+                }
+
+                let (index, spec) = match placeholder.find(':') {
+                    Some(colon) => (&placeholder[..colon], &placeholder[colon + 1..]),
+                    None => (&placeholder[..], ""),
+                };
+
+                let index = if index.is_empty() {
+                    next_argument += 1;
+                    next_argument - 1
+                } else {
+                    index.parse().map_err(|_| invalid(format!("Invalid placeholder {{{}}} in format string", placeholder)))?
+                };
+
+                let value = args.get(index).ok_or_else(|| invalid(format!("There is no argument {} for the format string", index)))?;
+                let spec = FormatSpec::parse(spec).map_err(invalid)?;
+                result.push_str(&spec.apply(value));
+            }
+            '}' => return Err(invalid("Single } in format string, use }} to get one".into())),
+            c => result.push(c),
+        }
+    }
+
+    Ok(result)
+}
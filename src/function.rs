@@ -1,24 +1,73 @@
 use crate::error::SantaError;
-use crate::eval::{eval_block_with_scope, Scope};
+use crate::eval::{check_stack, eval_block_with_scope, flow_to_value, Scope};
 use crate::function::Function::{Builtin, User};
-use crate::object::Object;
-use crate::parser::AstNode;
-use std::fmt::{Debug, Formatter, Error};
+use crate::object::{Object, StructType};
+use crate::parser::{AstNode, Pattern};
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter, Error};
 use std::rc::Rc;
 use std::cell::RefCell;
 
+/// A parameter that can be given by position. A named one can also be given by keyword,
+/// a pattern like `[a, b]` takes its argument apart and can only be given by position.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parameter {
+    Name(String),
+    Pattern(Pattern),
+}
+
+impl Parameter {
+    /// The name the parameter can be given by as a keyword argument, if it has one.
+    pub fn name(&self) -> Option<&String> {
+        match self {
+            Self::Name(name) => Some(name),
+            Self::Pattern(_) => None,
+        }
+    }
+}
+
+impl Display for Parameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Self::Name(name) => write!(f, "{}", name),
+            Self::Pattern(pattern) => write!(f, "{}", pattern),
+        }
+    }
+}
+
 /// An ArgumentList is a a struct holding information about
 /// what parameters a function wants.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParameterList {
-    pub positional: Vec<String>,
+    /// The parameters that can be given by position, in order.
+    pub positional: Vec<Parameter>,
+    /// The default values of positional parameters, evaluated when the function is called without them.
+    pub defaults: HashMap<String, Box<AstNode>>,
+    /// The name of the `*rest` parameter, which gets a list of the extra positional arguments.
+    pub rest: Option<String>,
+    /// The name of the `**opts` parameter, which gets a map of the extra keyword arguments.
+    pub keyword_rest: Option<String>,
 }
 
 impl ParameterList {
     pub fn new(positional: Vec<String>) -> Self {
-        Self { positional }
+        Self {
+            positional: positional.into_iter().map(Parameter::Name).collect(),
+            defaults: HashMap::new(),
+            rest: None,
+            keyword_rest: None,
+        }
+    }
+
+    /// A parameter list that takes any number of positional arguments after the given ones.
+    pub fn with_rest(positional: Vec<String>, rest: &str) -> Self {
+        Self {
+            rest: Some(rest.into()),
+            ..Self::new(positional)
+        }
     }
-    pub fn empty() -> Self {Self { positional: vec![]}}
+
+    pub fn empty() -> Self {Self::new(vec![])}
 }
 
 /// A ParameterList is a struct which are the parameters
@@ -26,31 +75,37 @@ impl ParameterList {
 #[derive(Debug, Clone, PartialEq)]
 pub struct ArgumentList {
     pub positional: Vec<Object>,
+    /// Arguments given by name like `f(b: 5)`, in the order they were given.
+    pub keywords: Vec<(String, Object)>,
 }
 
 impl ArgumentList {
     pub fn new(positional: Vec<Object>) -> Self {
-        Self { positional }
+        Self { positional, keywords: vec![] }
     }
 }
 
 #[derive(Clone)]
 pub enum Function {
-    Builtin(ParameterList, fn(Rc<RefCell<Scope>>) -> Result<Object, SantaError>),
-    User(ParameterList, Rc<RefCell<Scope>>, Vec<Box<AstNode>>),
+    Builtin(Rc<ParameterList>, fn(Rc<RefCell<Scope>>) -> Result<Object, SantaError>),
+    /// A function written in santa, with the name it was defined with (if any) for use in tracebacks.
+    User(Rc<ParameterList>, Rc<RefCell<Scope>>, Vec<Box<AstNode>>, Option<String>),
+    /// Makes a new instance of a struct, taking the values of its fields as arguments.
+    Constructor(Rc<StructType>),
 }
 
 impl Debug for Function {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         match self {
             Builtin(_, b) => write!(f, "Builtin function at {:p}", b),
-            User(args, _closure,  _) => write!(
+            User(args, _closure,  _, _) => write!(
                 f,
                 "Function({})",
                 args.positional
                     .iter()
                     .fold(String::new(), |acc, num| acc + &num.to_string() + ", ")
             ),
+            Self::Constructor(structtype) => write!(f, "Struct({})", structtype.name),
         }
     }
 }
@@ -65,13 +120,16 @@ impl PartialEq for Function {
                     false
                 }
             }
-            Self::User(argsu, _closure, u) => {
-                if let Self::User(argsou, _closure, ou) = other {
+            Self::User(argsu, _closure, u, _) => {
+                if let Self::User(argsou, _closure, ou, _) = other {
                     u == ou && argsu == argsou
                 } else {
                     false
                 }
             }
+            Self::Constructor(structtype) => {
+                matches!(other, Self::Constructor(other) if Rc::ptr_eq(structtype, other))
+            }
         }
     }
 }
@@ -82,14 +140,30 @@ impl Function {
         match self {
             Self::Builtin(params, b) => {
                 let scope = Scope::new();
-                scope.borrow_mut().load_arglist(argumentlist, params.clone())?;
+                Scope::load_arglist(&scope, argumentlist, params)?;
                 b(scope)
             }
-            Self::User(params, closure, ast) => {
-                let scope = Scope::child(closure.clone());
-                scope.borrow_mut().load_arglist(argumentlist, params.clone())?;
-                let a = eval_block_with_scope(&ast, scope);
-                a
+            Self::User(params, closure, ast, name) => {
+                check_stack()?;
+                let scope = Scope::function_child(closure.clone());
+                Scope::load_arglist(&scope, argumentlist, params)?;
+
+                let name = name.as_deref().unwrap_or("<anonymous function>");
+                flow_to_value(eval_block_with_scope(ast, scope).map_err(|e| e.in_function(name))?)
+            }
+            Self::Constructor(structtype) => {
+                let scope = Scope::bare();
+                let params = ParameterList::new(structtype.fields.clone());
+                Scope::load_arglist(&scope, argumentlist, &params).map_err(|e| e.in_function(&structtype.name))?;
+
+                let scope = scope.borrow();
+                let values = structtype
+                    .fields
+                    .iter()
+                    .map(|field| scope.get_variable(field).unwrap_or(Object::None))
+                    .collect();
+
+                Ok(Object::Struct(structtype.clone(), Rc::new(RefCell::new(values))))
             }
         }
     }
@@ -1,19 +1,23 @@
 #![allow(clippy::vec_box)]
 #![allow(clippy::boxed_local)]
 #![allow(clippy::ptr_arg)]
+#![allow(clippy::mutable_key_type)]
 
 use crate::manual::{get_manual_id, run_manual, set_manual_id, MANUAL_ID};
-use crate::parser::parse_string_or_panic;
+use crate::parser::parse_file;
 use clap::{App, AppSettings, Arg, SubCommand};
 use std::fs;
 
 mod builtins;
 mod error;
 mod eval;
+mod format;
 mod function;
 mod manual;
+mod modules;
 mod object;
 mod parser;
+mod span;
 mod database;
 mod networking;
 
@@ -48,13 +52,22 @@ fn main() {
         ("run", Some(matches)) => {
             let filename = matches
                 .value_of("filename")
-                .expect("Santa couldn't read your filename!");
+                .expect("Santa couldn't read your filename!")
+                .to_string();
             let file =
-                fs::read_to_string(filename).expect("Santa's elves couldn't find your file!");
-
-            let ast = parse_string_or_panic(&file);
-
-            eval::eval(ast);
+                fs::read_to_string(&filename).expect("Santa's elves couldn't find your file!");
+
+            eval::run_with_stack(move || {
+                let ast = match parse_file(&filename, &file) {
+                    Ok(ast) => ast,
+                    Err(e) => {
+                        eprint!("{}", e.report());
+                        std::process::exit(1);
+                    }
+                };
+
+                eval::eval(ast);
+            });
         }
         ("manual", Some(_)) => run_manual(),
         ("RESET", Some(matches)) => {
@@ -67,11 +80,11 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use crate::eval::{eval_node, eval_with_scope, Scope, eval_with_scope_err};
-    use crate::function::{Function, ParameterList};
+    use crate::eval::{eval_node, eval_with_scope, Scope, eval_with_scope_err, run_with_stack};
+    use crate::function::{Function, Parameter, ParameterList};
     use crate::object::Object;
     use crate::parser::AstNode::{Assignment, Expression, Integer, Name};
-    use crate::parser::{parse_string_or_panic, BinaryOperator, Operator, UnaryOperator};
+    use crate::parser::{parse_string, parse_string_or_panic, AstNode, BinaryOperator, Operator, UnaryOperator};
     use std::collections::HashMap;
     use std::rc::Rc;
     use std::cell::RefCell;
@@ -212,6 +225,186 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_division_2() {
+        let ast = parse_string_or_panic("a = 3 / 0;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_floordivision_1() {
+        let ast = parse_string_or_panic(
+            "
+assert(7 // 2 == 3);
+assert(-7 // 2 == -4);
+assert(7 // -2 == -4);
+assert(7.5 // 2 == 3.0);
+assert(1 // 0.5 == 2.0);
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Ok(Object::None));
+
+        let ast = parse_string_or_panic("a = 7 // 2;");
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Ok(Object::Integer(3)));
+
+        let ast = parse_string_or_panic("a = 7 // false;");
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_floordivision_2() {
+        // `//` between two values divides, anywhere else it starts a comment
+        let ast = parse_string_or_panic("
+// a comment //= with operators // in it
+//==== and a banner ====
+a = 17 // 2 // 3; // 2
+a //= 2;
+b = 9
+    /* a block comment */ //
+    // a comment between the operator and the value
+    2 * 3;
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::Integer(1)));
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(Object::Integer(12)));
+    }
+
+    #[test]
+    fn test_modulo_1() {
+        let ast = parse_string_or_panic(
+            "
+assert(7 % 3 == 1);
+assert(-7 % 3 == 2);
+assert(7 % -3 == -2);
+assert(5.5 % 2 == 1.5);
+assert(3 * 4 % 5 == 2);
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Ok(Object::None));
+
+        let ast = parse_string_or_panic("a = 7 % 0;");
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_power_1() {
+        let ast = parse_string_or_panic("a = -2 ** 3 ** 2;");
+
+        assert_eq!(
+            ast,
+            vec![Box::new(Assignment {
+                name: Box::new(Name("a".into())),
+                expression: Box::new(Expression(Operator::Unary {
+                    operator: UnaryOperator::Negate,
+                    expr: Box::new(Expression(Operator::Binary {
+                        operator: BinaryOperator::Power,
+                        lhs: Box::new(Integer(2)),
+                        rhs: Box::new(Expression(Operator::Binary {
+                            operator: BinaryOperator::Power,
+                            lhs: Box::new(Integer(3)),
+                            rhs: Box::new(Integer(2)),
+                        })),
+                    })),
+                })),
+                indexes: vec![]
+            })]
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Ok(Object::Integer(-512)));
+    }
+
+    #[test]
+    fn test_power_2() {
+        let ast = parse_string_or_panic(
+            "
+assert(2 ** -1 == 0.5);
+assert(4 ** 0.5 == 2);
+assert(2 * 3 ** 2 == 18);
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Ok(Object::None));
+
+        let ast = parse_string_or_panic("a = 0 ** -1;");
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_bitwise_1() {
+        let ast = parse_string_or_panic(
+            "
+assert(12 & 10 == 8);
+assert(12 | 10 == 14);
+assert(12 ^ 10 == 6);
+assert(1 << 4 == 16);
+assert(-16 >> 2 == -4);
+assert(1 | 2 ^ 3 & 4 == 3);
+assert(1 + 1 << 2 == 8);
+assert((true ^ true) == false);
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Ok(Object::None));
+
+        let ast = parse_string_or_panic("a = 1 << 64;");
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::InvalidOperationError {cause: "shift by 64 out of range".into()}));
+    }
+
+    #[test]
+    fn test_overflow_1() {
+        for code in &[
+            "a = 9223372036854775807 + 1;",
+            "a = -9223372036854775807 - 2;",
+            "a = 4294967296 * 4294967296;",
+            "a = -(-9223372036854775807 - 1);",
+            "a = (-9223372036854775807 - 1) // -1;",
+            "a = 3 ** 40;",
+            "a = 2 ** 4294967296;",
+            "a = 3 << 62;",
+        ] {
+            let ast = parse_string_or_panic(code);
+            let scope = Scope::new();
+            assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::IntegerOverflow), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_overflow_2() {
+        let ast = parse_string_or_panic(
+            "
+assert(9223372036854775806 + 1 == 9223372036854775807);
+assert((-1) ** 4294967297 == -1);
+assert(1 << 62 == 4611686018427387904);
+assert(\"yeet\" * -1 == \"\");
+assert(9007199254740993 != 9007199254740992.0);
+assert(9007199254740993 > 9007199254740992.0);
+assert(9223372036854775807 < 9223372036854775808.0);
+assert(2.5 > 2);
+assert(-2.5 < -2);
+assert(2.0 == 2);
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Ok(Object::None));
+    }
+
     #[test]
     fn test_negation_1() {
         let ast = parse_string_or_panic("a = -3;");
@@ -359,10 +552,10 @@ mod tests {
         // create a function called a
         scope.borrow_mut().set_variable(
             "a".into(),
-            Object::Function(Function::Builtin(ParameterList::new(vec![]), |_| {
+            Object::Function(Function::Builtin(Rc::new(ParameterList::new(vec![])), |_| {
                 Ok(Object::Integer(10))
             })),
-        );
+        ).unwrap();
 
         assert_eq!(
             eval_node(nodes.next().unwrap().as_ref(), scope.clone()).unwrap(),
@@ -386,13 +579,13 @@ mod tests {
         scope.borrow_mut().set_variable(
             "a".into(),
             Object::Function(Function::Builtin(
-                ParameterList::new(vec!["x".into()]),
+                Rc::new(ParameterList::new(vec!["x".into()])),
                 |scope| match scope.borrow().get_variable(&"x".into()) {
                     Some(i) => Ok(i),
                     None => Ok(Object::None),
                 },
             )),
-        );
+        ).unwrap();
 
         assert_eq!(
             eval_node(nodes.next().unwrap().as_ref(), scope.clone()).unwrap(),
@@ -419,8 +612,8 @@ function a (x) {
 
         let var = scope.borrow().get_variable(&"a".into());
         match var {
-            Some(Object::Function(Function::User(x, closure, _))) => {
-                assert_eq!(x.positional, vec![String::from("x")]);
+            Some(Object::Function(Function::User(x, closure, _, _))) => {
+                assert_eq!(x.positional, vec![Parameter::Name("x".into())]);
             }
             _ => panic!(),
         }
@@ -446,8 +639,8 @@ a(3);
 
         let var = scope.borrow().get_variable(&"a".into());
         match var {
-            Some(Object::Function(Function::User(x, closure, _))) => {
-                assert_eq!(x.positional, vec![String::from("x")]);
+            Some(Object::Function(Function::User(x, closure, _, _))) => {
+                assert_eq!(x.positional, vec![Parameter::Name("x".into())]);
             }
             _ => panic!(),
         }
@@ -473,8 +666,8 @@ a(3);
 
         let var = scope.borrow().get_variable(&"a".into());
         match var {
-            Some(Object::Function(Function::User(x, closure, _))) => {
-                assert_eq!(x.positional, vec![String::from("x")]);
+            Some(Object::Function(Function::User(x, closure, _, _))) => {
+                assert_eq!(x.positional, vec![Parameter::Name("x".into())]);
             }
             _ => panic!(),
         }
@@ -704,117 +897,1871 @@ a = \"test\";
     }
 
     #[test]
-    fn test_comparison_1() {
-        let ast = parse_string_or_panic("3==5;");
+    fn test_string_2() {
+        let ast = parse_string_or_panic(r#"
+a = "say ""hi""\n\t\\ \"x\" \u{1F385}";
+b = 'it''s \'x\'';
+"#);
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(false));
+        eval_with_scope(ast, scope.clone());
+
+        assert_eq!(
+            scope.borrow().get_variable(&"a".into()),
+            Some(Object::String("say \"hi\"\n\t\\ \"x\" \u{1F385}".into()))
+        );
+        assert_eq!(
+            scope.borrow().get_variable(&"b".into()),
+            Some(Object::String("it's 'x'".into()))
+        );
     }
 
     #[test]
-    fn test_comparison_2() {
-        let ast = parse_string_or_panic("3==3;");
+    fn test_string_3() {
+        let ast = parse_string_or_panic(r#"
+a = r"C:\new\table";
+b = r'''no "escapes" \n''';
+"#);
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+        eval_with_scope(ast, scope.clone());
+
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::String(r"C:\new\table".into())));
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(Object::String(r#"no "escapes" \n"#.into())));
     }
 
     #[test]
-    fn test_comparison_3() {
-        let ast = parse_string_or_panic("3==3.0;");
+    fn test_string_4() {
+        let ast = parse_string_or_panic(r#"
+a = """line "one"
+line\ttwo""";
+b = '''it's''';
+"#);
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+        eval_with_scope(ast, scope.clone());
+
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::String("line \"one\"\nline\ttwo".into())));
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(Object::String("it's".into())));
     }
 
     #[test]
-    fn test_comparison_4() {
-        let ast = parse_string_or_panic("3.0==3;");
+    fn test_string_5() {
+        for code in &[r#"a = "\q";"#, r#"a = "\u{110000}";"#, r#"a = "\u{41";"#, r#"a = "\u41";"#] {
+            match parse_string(code).as_ref().map_err(SantaError::without_location) {
+                Err(SantaError::ParseError { .. }) => (),
+                other => panic!("{} parsed to {:?}", code, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_span_1() {
+        let ast = parse_string_or_panic("a = [1, 2];\nb = 3 + a[5];");
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+        let error = eval_with_scope_err(ast, scope).unwrap_err();
+        assert_eq!(error, SantaError::IndexOutOfBounds);
+
+        match &error {
+            SantaError::Located { span, .. } => {
+                assert_eq!(span.line_col(), (2, 9));
+                assert_eq!(&span.source.text[span.start..span.end], "a[5]");
+                assert_eq!(span.line_text(), "b = 3 + a[5];");
+            }
+            other => panic!("{:?} has no location", other),
+        }
     }
 
     #[test]
-    fn test_comparison_5() {
-        let ast = parse_string_or_panic("3 > 3;");
+    fn test_span_2() {
+        let ast = parse_string_or_panic("x = 1;\n\nif x == 1 {\n    y = x / 0;\n}");
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(false));
+        let report = eval_with_scope_err(ast, scope).unwrap_err().report();
+        assert!(report.contains("Division by zero"), "{}", report);
+        assert!(report.contains("<string>:4:9"), "{}", report);
+        assert!(report.contains("    y = x / 0;"), "{}", report);
+        assert!(report.contains("^^^^^"), "{}", report);
     }
 
     #[test]
-    fn test_comparison_6() {
-        let ast = parse_string_or_panic("3 < 3;");
+    fn test_span_3() {
+        let error = parse_string("a = 1;\nb = \"\\q\";").unwrap_err();
+        let report = error.report();
+        assert!(report.contains("Invalid escape sequence"), "{}", report);
+        assert!(report.contains("<string>:2:5"), "{}", report);
+    }
+
+    #[test]
+    fn test_traceback_1() {
+        let ast = parse_string_or_panic("
+function inner(x) {
+    yeet x / 0 back;
+}
+
+function outer(y) {
+    yeet inner(y) + 1 back;
+}
+
+outer(3);
+");
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(false));
+        let error = eval_with_scope_err(ast, scope).unwrap_err();
+        assert_eq!(error, SantaError::DivisionByZero);
+
+        let frames: Vec<_> = error
+            .traceback()
+            .into_iter()
+            .map(|(name, span)| (name, span.map(|i| i.line_col())))
+            .collect();
+        assert_eq!(
+            frames,
+            vec![
+                ("<main>".into(), Some((10, 1))),
+                ("outer".into(), Some((7, 10))),
+                ("inner".into(), Some((3, 10))),
+            ]
+        );
+
+        let report = error.report();
+        assert!(report.contains("in outer at <string>:7:10"), "{}", report);
+        assert!(report.contains("yeet inner(y) + 1 back;"), "{}", report);
     }
 
     #[test]
-    fn test_comparison_8() {
-        let ast = parse_string_or_panic("3 >= 3;");
+    fn test_traceback_2() {
+        let ast = parse_string_or_panic("
+f = function() {
+    yeet [][0] back;
+};
+
+f();
+");
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+        let error = eval_with_scope_err(ast, scope).unwrap_err();
+        assert_eq!(error, SantaError::IndexOutOfBounds);
+
+        let names: Vec<_> = error.traceback().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec![String::from("<main>"), String::from("<anonymous function>")]);
     }
 
     #[test]
-    fn test_comparison_9() {
-        let ast = parse_string_or_panic("3 <= 3;");
+    fn test_try_1() {
+        let ast = parse_string_or_panic("
+kind = 0;
+message = 0;
+try {
+    a = [1, 2][5];
+} catch err {
+    kind = err[\"kind\"];
+    message = err[\"message\"];
+}
+");
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"kind".into()), Some(Object::String("IndexOutOfBounds".into())));
+        assert_eq!(scope.borrow().get_variable(&"message".into()), Some(Object::String("Index out of bounds".into())));
     }
 
     #[test]
-    fn test_comparison_10() {
-        let ast = parse_string_or_panic("4 > 3;");
+    fn test_try_2() {
+        let ast = parse_string_or_panic("
+log = [];
+function f(x) {
+    try {
+        if x == 0 {
+            raise \"zero\";
+        }
+        yeet x back;
+    } catch e {
+        yeet e[\"message\"] back;
+    } finally {
+        log = log + [x];
+    }
+}
+a = f(0);
+b = f(3);
+");
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::String("zero".into())));
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(Object::Integer(3)));
+        assert_eq!(
+            scope.borrow().get_variable(&"log".into()),
+            Some(Object::List(Rc::new(RefCell::new(vec![Object::Integer(0), Object::Integer(3)]))))
+        );
     }
 
     #[test]
-    fn test_comparison_11() {
-        let ast = parse_string_or_panic("3 < 4;");
+    fn test_try_3() {
+        let ast = parse_string_or_panic("
+cleaned = false;
+try {
+    throw {\"kind\": \"NaughtyError\", \"message\": \"coal\"};
+} finally {
+    cleaned = true;
+}
+");
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+        assert_eq!(
+            eval_with_scope_err(ast, scope.clone()),
+            Err(SantaError::Raised { kind: "NaughtyError".into(), message: "coal".into() })
+        );
+        assert_eq!(scope.borrow().get_variable(&"cleaned".into()), Some(Object::Boolean(true)));
     }
 
     #[test]
-    fn test_comparison_12() {
-        let ast = parse_string_or_panic("3 != 4;");
+    fn test_try_4() {
+        // Errors can be raised again after catching them
+        let ast = parse_string_or_panic("
+try {
+    1 / 0;
+} catch err {
+    raise err;
+}
+");
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+        let error = eval_with_scope_err(ast, scope).unwrap_err();
+        assert_eq!(error.kind(), "DivisionByZero");
+        assert_eq!(error.message(), "Division by zero");
+
+        assert!(parse_string("try { a = 1; }").is_err());
+        assert!(parse_string("raised = 1; raised;").is_ok());
+    }
+
+    /// Writes santa files to a fresh directory for the import tests, and yeets back its path.
+    fn write_modules(test: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+        let directory = std::env::temp_dir().join(format!("santa_{}_{}", test, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        for (name, code) in files {
+            std::fs::write(directory.join(name), code).unwrap();
+        }
+
+        directory
     }
 
     #[test]
-    fn test_comparison_13() {
-        let ast = parse_string_or_panic("3 != 3;");
+    fn test_import_1() {
+        let directory = write_modules("import_1", &[
+            ("helpers.santa", "import \"util.santa\";\nfunction double(x) { yeet twice(x) back; }\nitems = [1];"),
+            ("util.santa", "function twice(x) { yeet x * 2 back; }"),
+        ]);
+        let helpers = directory.join("helpers.santa");
+
+        let ast = parse_string_or_panic(&format!("
+import {0:?};
+a = double(4);
+items[0] = 5;
+import items from {0:?};
+b = items[0];
+", helpers.to_string_lossy()));
 
         let scope = Scope::new();
-        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(false));
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::Integer(8)));
+        // The module is only evaluated once, so its list is still the changed one
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(Object::Integer(5)));
+        // twice was imported into helpers, so it is imported from there too
+        assert!(scope.borrow().get_variable(&"twice".into()).is_some());
     }
 
     #[test]
-    fn test_while_1() {
-        let ast = parse_string_or_panic(
-            "
-a = 0;
-while a < 10 {
-    a = a + 1;
+    fn test_import_2() {
+        let directory = write_modules("import_2", &[
+            ("a.santa", "import \"b.santa\";"),
+            ("b.santa", "import \"a.santa\";"),
+            ("c.santa", "x = 1;"),
+        ]);
+
+        let ast = parse_string_or_panic(&format!("import {:?};", directory.join("a.santa").to_string_lossy()));
+        let error = eval_with_scope_err(ast, Scope::new()).unwrap_err();
+        assert_eq!(error.kind(), "ImportError");
+        assert!(error.message().contains("circular import"), "{}", error);
+
+        let ast = parse_string_or_panic(&format!("import y from {:?};", directory.join("c.santa").to_string_lossy()));
+        assert_eq!(eval_with_scope_err(ast, Scope::new()).unwrap_err().kind(), "ImportError");
+
+        let ast = parse_string_or_panic(&format!("import {:?};", directory.join("missing.santa").to_string_lossy()));
+        assert_eq!(eval_with_scope_err(ast, Scope::new()).unwrap_err().kind(), "ImportError");
+    }
+
+    #[test]
+    fn test_import_3() {
+        // Importing inside a function doesn't change the variables outside of it
+        let directory = write_modules("import_3", &[("lib.santa", "helper = \"theirs\";")]);
+
+        let ast = parse_string_or_panic(&format!("
+helper = \"mine\";
+function f() {{
+    import helper from {:?};
+    yeet helper back;
+}}
+[f(), helper];
+", directory.join("lib.santa").to_string_lossy()));
+
+        let list = vec![Object::String("theirs".into()), Object::String("mine".into())];
+        assert_eq!(eval_with_scope(ast, Scope::new()), Object::List(Rc::new(RefCell::new(list))));
+
+        // Constants of a module are constant where they are imported too
+        let directory = write_modules("import_3", &[("m.santa", "const LIMIT = 3;")]);
+        let ast = parse_string_or_panic(&format!("import LIMIT from {:?}; LIMIT = 4;", directory.join("m.santa").to_string_lossy()));
+        assert_eq!(
+            eval_with_scope_err(ast, Scope::new()),
+            Err(SantaError::InvalidOperationError { cause: "LIMIT is a constant and can't be assigned to".into() })
+        );
+    }
+
+    #[test]
+    fn test_let_1() {
+        let ast = parse_string_or_panic("
+index = 10;
+x = 1;
+function count(list) {
+    let index = 0;
+    for i in list {
+        index = index + 1;
+    }
+    yeet index back;
+}
+function identity(x) {
+    yeet x back;
 }
+a = count([1, 2, 3]);
+b = identity(5);
+");
 
-            ",
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::Integer(3)));
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(Object::Integer(5)));
+        // Neither the let nor the parameter changed the globals
+        assert_eq!(scope.borrow().get_variable(&"index".into()), Some(Object::Integer(10)));
+        assert_eq!(scope.borrow().get_variable(&"x".into()), Some(Object::Integer(1)));
+    }
+
+    #[test]
+    fn test_let_2() {
+        let ast = parse_string_or_panic("
+counter = 0;
+function outer() {
+    let counter = 100;
+    function increment() {
+        global counter;
+        counter = counter + 1;
+    }
+    function bump() {
+        nonlocal counter;
+        counter = counter + 5;
+    }
+    increment();
+    bump();
+    yeet counter back;
+}
+inner = outer();
+function make_total() {
+    global total;
+    total = 7;
+}
+make_total();
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"counter".into()), Some(Object::Integer(1)));
+        assert_eq!(scope.borrow().get_variable(&"inner".into()), Some(Object::Integer(105)));
+        assert_eq!(scope.borrow().get_variable(&"total".into()), Some(Object::Integer(7)));
+    }
+
+    #[test]
+    fn test_strict_1() {
+        let ast = parse_string_or_panic("
+strict;
+let a = 1;
+a = 2;
+let b;
+if a == 2 {
+    b = a;
+}
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(Object::Integer(2)));
+
+        let ast = parse_string_or_panic("strict;\nfunction f() {\n    typo = 1;\n}\nf();");
+        assert_eq!(
+            eval_with_scope_err(ast, Scope::new()),
+            Err(SantaError::InvalidOperationError {
+                cause: "typo was never declared, use `let typo = ...;` to declare it".into()
+            })
+        );
+
+        let ast = parse_string_or_panic("function f() { nonlocal missing; } f();");
+        assert!(eval_with_scope_err(ast, Scope::new()).is_err());
+    }
+
+    #[test]
+    fn test_strict_2() {
+        // A helper function can't clobber a variable outside of it by accident
+        let ast = parse_string_or_panic("strict; let index = 1; function helper() { index = 99; } helper();");
+        assert_eq!(
+            eval_with_scope_err(ast, Scope::new()),
+            Err(SantaError::InvalidOperationError {
+                cause: "index belongs outside this function, use `global index` or `nonlocal index` to assign to it".into()
+            })
+        );
+
+        let ast = parse_string_or_panic("
+strict;
+let index = 1;
+function helper() {
+    global index;
+    let count = 0;
+    for i in [1, 2] {
+        count += i;
+    }
+    index = count;
+}
+helper();
+index;
+");
+        assert_eq!(eval_with_scope(ast, Scope::new()), Object::Integer(3));
+    }
+
+    #[test]
+    fn test_strict_3() {
+        // Functions and structs defined inside a function belong to that function
+        let ast = parse_string_or_panic("
+strict;
+let v = 1;
+let s = 5;
+function g() {
+    function v() { yeet 2 back; }
+    struct s { x }
+    yeet [v(), s(3).x] back;
+}
+[g(), v, s];
+");
+        let list = |items: Vec<Object>| Object::List(Rc::new(RefCell::new(items)));
+        assert_eq!(
+            eval_with_scope(ast, Scope::new()),
+            list(vec![list(vec![Object::Integer(2), Object::Integer(3)]), Object::Integer(1), Object::Integer(5)])
         );
+    }
+
+    #[test]
+    fn test_keyword_1() {
+        let ast = parse_string_or_panic("
+function f(a, b = a * 2, *rest, **opts) {
+    yeet [a, b, rest, opts] back;
+}
+x = f(1);
+y = f(1, 5, 6, x: 3);
+z = f(b: 4, a: 2);
+l = len(value: [1, 2, 3]);
+");
 
         let scope = Scope::new();
         eval_with_scope(ast, scope.clone());
 
-        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::Integer(10)));
+        let list = |items: Vec<Object>| Object::List(Rc::new(RefCell::new(items)));
+        let empty_map = Object::Map(Rc::new(RefCell::new(HashMap::new())));
+        let mut opts = HashMap::new();
+        opts.insert(Object::String("x".into()), Object::Integer(3));
+
+        assert_eq!(
+            scope.borrow().get_variable(&"x".into()),
+            Some(list(vec![Object::Integer(1), Object::Integer(2), list(vec![]), empty_map.clone()]))
+        );
+        assert_eq!(
+            scope.borrow().get_variable(&"y".into()),
+            Some(list(vec![
+                Object::Integer(1),
+                Object::Integer(5),
+                list(vec![Object::Integer(6)]),
+                Object::Map(Rc::new(RefCell::new(opts)))
+            ]))
+        );
+        assert_eq!(
+            scope.borrow().get_variable(&"z".into()),
+            Some(list(vec![Object::Integer(2), Object::Integer(4), list(vec![]), empty_map]))
+        );
+        assert_eq!(scope.borrow().get_variable(&"l".into()), Some(Object::Integer(3)));
+    }
+
+    #[test]
+    fn test_keyword_2() {
+        let ast = parse_string_or_panic("function f(a, b = 1) { yeet a back; }\nf(1, c: 2);");
+        assert_eq!(
+            eval_with_scope_err(ast, Scope::new()),
+            Err(SantaError::InvalidOperationError {cause: "Unknown keyword argument c".into()})
+        );
+
+        let ast = parse_string_or_panic("function f(a, b = 1) { yeet a back; }\nf(1, a: 2);");
+        assert_eq!(
+            eval_with_scope_err(ast, Scope::new()),
+            Err(SantaError::InvalidOperationError {cause: "Got multiple values for argument a".into()})
+        );
+
+        let ast = parse_string_or_panic("function f(a, b = 1) { yeet a back; }\nf(b: 2);");
+        assert_eq!(
+            eval_with_scope_err(ast, Scope::new()),
+            Err(SantaError::InvalidOperationError {cause: "Not enough arguments for function".into()})
+        );
+
+        for code in &["function f(a = 1, b) {}", "function f(*a, b) {}", "function f(**a, *b) {}", "function f(a, a) {}", "f(a: 1, 2);"] {
+            assert!(parse_string(code).is_err(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_spread_1() {
+        let ast = parse_string_or_panic("
+function f(a, b = 0, *rest, **opts) {
+    yeet [a, b, len(rest), len(opts)] back;
+}
+function wrapper(*args, **kwargs) {
+    yeet f(*args, **kwargs) back;
+}
+x = wrapper(1, 2, 3, 4);
+y = f(*[5], **{\"b\": 6, \"c\": 7});
+z = f(*range(3), *[1, 2]);
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        let ints = |items: &[i64]| Object::List(Rc::new(RefCell::new(items.iter().map(|i| Object::Integer(*i)).collect())));
+        assert_eq!(scope.borrow().get_variable(&"x".into()), Some(ints(&[1, 2, 2, 0])));
+        assert_eq!(scope.borrow().get_variable(&"y".into()), Some(ints(&[5, 6, 0, 1])));
+        assert_eq!(scope.borrow().get_variable(&"z".into()), Some(ints(&[0, 1, 3, 0])));
+    }
+
+    #[test]
+    fn test_spread_2() {
+        let ast = parse_string_or_panic("function f(*a) { yeet a back; }\nf(**[1]);");
+        assert!(eval_with_scope_err(ast, Scope::new()).is_err());
+
+        let ast = parse_string_or_panic("function f(**a) { yeet a back; }\nf(**{1: 2});");
+        assert!(eval_with_scope_err(ast, Scope::new()).is_err());
+
+        let ast = parse_string_or_panic("function f(*a) { yeet a back; }\nf(*5);");
+        assert!(eval_with_scope_err(ast, Scope::new()).is_err());
+
+        assert!(parse_string("f(**{}, 1);").is_err());
+    }
+
+    #[test]
+    fn test_else_if_1() {
+        let ast = parse_string_or_panic("
+function digit(c) {
+    yeet if c == \"1\" {
+        1;
+    } else if c == \"2\" {
+        2;
+    } else if c == \"3\" {
+        3;
+    } else {
+        0;
+    } back;
+}
+a = digit(\"1\");
+b = digit(\"3\");
+c = digit(\"x\");
+d = 0;
+if false {
+    d = 1;
+} else if true {
+    d = 2;
+}
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::Integer(1)));
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(Object::Integer(3)));
+        assert_eq!(scope.borrow().get_variable(&"c".into()), Some(Object::Integer(0)));
+        assert_eq!(scope.borrow().get_variable(&"d".into()), Some(Object::Integer(2)));
+    }
+
+    #[test]
+    fn test_else_if_2() {
+        assert_eq!(
+            parse_string_or_panic("if a { 1; } else if b { 2; }"),
+            parse_string_or_panic("if a { 1; } else { if b { 2; } }")
+        );
+    }
+
+    #[test]
+    fn test_slice_1() {
+        let ast = parse_string_or_panic("
+a = [0, 1, 2, 3, 4, 5];
+b = [a[-1], a[1:3], a[:2], a[4:], a[::-1], a[5:1:-2], a[10:], a[-100:1]];
+s = \"santa\";
+c = [s[-1], s[1:3], s[::-1], s[::2]];
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        let list = |items: Vec<Object>| Object::List(Rc::new(RefCell::new(items)));
+        let ints = |items: &[i64]| list(items.iter().map(|i| Object::Integer(*i)).collect());
+        assert_eq!(
+            scope.borrow().get_variable(&"b".into()),
+            Some(list(vec![
+                Object::Integer(5),
+                ints(&[1, 2]),
+                ints(&[0, 1]),
+                ints(&[4, 5]),
+                ints(&[5, 4, 3, 2, 1, 0]),
+                ints(&[5, 3]),
+                ints(&[]),
+                ints(&[0]),
+            ]))
+        );
+        assert_eq!(
+            scope.borrow().get_variable(&"c".into()),
+            Some(list(vec![
+                Object::String("a".into()),
+                Object::String("an".into()),
+                Object::String("atnas".into()),
+                Object::String("sna".into()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_slice_2() {
+        let ast = parse_string_or_panic("
+a = [0, 1, 2, 3, 4, 5];
+a[-1] = 50;
+a[1:3] = [10];
+b = [0, 1, 2, 3, 4];
+b[::2] = [7, 8, 9];
+c = [1, 2];
+c[1:1] = c;
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        let ints = |items: &[i64]| Object::List(Rc::new(RefCell::new(items.iter().map(|i| Object::Integer(*i)).collect())));
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(ints(&[0, 10, 3, 4, 50])));
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(ints(&[7, 1, 8, 3, 9])));
+        assert_eq!(scope.borrow().get_variable(&"c".into()), Some(ints(&[1, 1, 2, 2])));
+
+        for code in &["[1, 2][-3];", "[1][::0];", "a = [1, 2, 3];\na[::2] = [1];", "[1][\"a\":];"] {
+            let ast = parse_string_or_panic(code);
+            assert!(eval_with_scope_err(ast, Scope::new()).is_err(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_unicode_1() {
+        let ast = parse_string_or_panic("
+s = \"Zoë Noël\";
+copy = \"\";
+i = 0;
+while i < len(s) {
+    copy = copy + s[i];
+    i = i + 1;
+}
+length = len(s);
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"copy".into()), Some(Object::String("Zoë Noël".into())));
+        assert_eq!(scope.borrow().get_variable(&"length".into()), Some(Object::Integer(8)));
+    }
+
+    #[test]
+    fn test_unicode_2() {
+        let ast = parse_string_or_panic("
+a = chars(\"e\\u{301}x\");
+b = chars(\"e\\u{301}x\", graphemes: true);
+c = bytes(\"é\");
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        let list = |items: Vec<Object>| Object::List(Rc::new(RefCell::new(items)));
+        let strings = |items: &[&str]| list(items.iter().map(|i| Object::String(i.to_string())).collect());
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(strings(&["e", "\u{301}", "x"])));
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(strings(&["e\u{301}", "x"])));
+        assert_eq!(scope.borrow().get_variable(&"c".into()), Some(list(vec![Object::Integer(195), Object::Integer(169)])));
+    }
+
+    #[test]
+    fn test_compound_assignment_1() {
+        let ast = parse_string_or_panic("
+a = 10;
+a += 5;
+a -= 1;
+a *= 3;
+a //= 4;
+a **= 2;
+b = 6;
+b &= 3;
+b <<= 2;
+s = \"ho\";
+s *= 3;
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::Integer(100)));
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(Object::Integer(8)));
+        assert_eq!(scope.borrow().get_variable(&"s".into()), Some(Object::String("hohoho".into())));
+    }
+
+    #[test]
+    fn test_compound_assignment_2() {
+        let ast = parse_string_or_panic("
+calls = 0;
+function key() {
+    global calls;
+    calls += 1;
+    yeet 1 back;
+}
+a = [[1, 2], [3, 4]];
+a[key()][key()] += 10;
+m = {\"presents\": 1};
+m[\"presents\"] *= 7;
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        let list = |items: Vec<Object>| Object::List(Rc::new(RefCell::new(items)));
+        assert_eq!(scope.borrow().get_variable(&"calls".into()), Some(Object::Integer(2)));
+        assert_eq!(
+            scope.borrow().get_variable(&"a".into()),
+            Some(list(vec![
+                list(vec![Object::Integer(1), Object::Integer(2)]),
+                list(vec![Object::Integer(3), Object::Integer(14)]),
+            ]))
+        );
+
+        let ast = parse_string_or_panic("m[\"presents\"];");
+        assert_eq!(eval_with_scope(ast, scope.clone()), Object::Integer(7));
+
+        let ast = parse_string_or_panic("missing += 1;");
+        assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::NoDefinitionError));
+    }
+
+    #[test]
+    fn test_destructuring_1() {
+        let ast = parse_string_or_panic("
+[id, name, naughty] = [3, \"Noël\", false];
+[first, *middle, last] = [1, 2, 3, 4];
+[[a, b], c] = [[5, 6], 7];
+record = {\"name\": \"Rudolph\", \"nose\": \"red\", \"legs\": 4};
+{name: reindeer, **others} = record;
+{nose} = record;
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        let list = |items: Vec<Object>| Object::List(Rc::new(RefCell::new(items)));
+        let get = |name: &str| scope.borrow().get_variable(&name.into());
+        assert_eq!(get("id"), Some(Object::Integer(3)));
+        assert_eq!(get("name"), Some(Object::String("Noël".into())));
+        assert_eq!(get("naughty"), Some(Object::Boolean(false)));
+        assert_eq!(get("first"), Some(Object::Integer(1)));
+        assert_eq!(get("middle"), Some(list(vec![Object::Integer(2), Object::Integer(3)])));
+        assert_eq!(get("last"), Some(Object::Integer(4)));
+        assert_eq!(get("a"), Some(Object::Integer(5)));
+        assert_eq!(get("b"), Some(Object::Integer(6)));
+        assert_eq!(get("c"), Some(Object::Integer(7)));
+        assert_eq!(get("reindeer"), Some(Object::String("Rudolph".into())));
+        assert_eq!(get("nose"), Some(Object::String("red".into())));
+
+        let ast = parse_string_or_panic("len(others);");
+        assert_eq!(eval_with_scope(ast, scope.clone()), Object::Integer(2));
+
+        let ast = parse_string_or_panic("[x, y] = [1, 2, 3];");
+        assert_eq!(
+            eval_with_scope_err(ast, scope.clone()),
+            Err(SantaError::InvalidOperationError { cause: "Expected 2 values to unpack, got 3".into() })
+        );
+
+        let ast = parse_string_or_panic("[x, *y, z] = [1];");
+        assert_eq!(
+            eval_with_scope_err(ast, scope.clone()),
+            Err(SantaError::InvalidOperationError { cause: "Expected at least 2 values to unpack, got 1".into() })
+        );
+
+        let ast = parse_string_or_panic("{wings} = record;");
+        assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::KeyError));
+    }
+
+    #[test]
+    fn test_destructuring_2() {
+        let ast = parse_string_or_panic("
+function describe([id, name], {isnaughty}, suffix = \"!\") {
+    if isnaughty {
+        yeet name + \" is naughty\" + suffix back;
+    }
+    yeet name + \" is nice\" + suffix back;
+}
+a = describe([1, \"Zoë\"], {\"isnaughty\": false});
+total = 0;
+for [id, count] in [[1, 2], [2, 5]] {
+    total = total + id * count;
+}
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::String("Zoë is nice!".into())));
+        assert_eq!(scope.borrow().get_variable(&"total".into()), Some(Object::Integer(12)));
+
+        assert_eq!(
+            parse_string("function f([a, b], a) {}"),
+            Err(SantaError::ParseError { cause: "Parameter a is used more than once".into() })
+        );
+
+        // A pattern parameter has no name, so it can't be given by keyword
+        let ast = parse_string_or_panic("function f([a, b], {c, d: e, **f}) { yeet a back; }\nf([3, 4], **{\"[a, b]\": [1, 2]});");
+        assert_eq!(
+            eval_with_scope_err(ast, Scope::new()),
+            Err(SantaError::InvalidOperationError {cause: "Unknown keyword argument [a, b]".into()})
+        );
+
+        let ast = parse_string_or_panic("function f([a, b], {c, d: e, **f}) { yeet a back; }\nf(5, {});");
+        assert_eq!(
+            eval_with_scope_err(ast, Scope::new()),
+            Err(SantaError::InvalidOperationError {
+                cause: "Couldn't take the argument of parameter [a, b] apart: iterating over Integer(5) not supported".into()
+            })
+        );
+
+        let ast = parse_string_or_panic("f = function ([a, b], {c, d: e, **f}) {};\nf;");
+        assert_eq!(eval_with_scope(ast, Scope::new()).to_string(), "Function([a, b], {c, d: e, **f}, )");
+    }
+
+    #[test]
+    fn test_match_1() {
+        let ast = parse_string_or_panic("
+function handle(message) {
+    yeet match message {
+        [\"naughty\", id] => \"naughty \" + id,
+        [\"rename\", id, name] if len(name) > 0 => \"rename \" + id + \" to \" + name,
+        [\"rename\", *_] => \"empty name\",
+        {kind: \"count\", value} => value * 2,
+        -1 => \"minus one\",
+        true => \"yes\",
+        other => \"unknown \" + other,
+    } back;
+}
+a = handle([\"naughty\", 3]);
+b = handle([\"rename\", 4, \"Rudolph\"]);
+c = handle([\"rename\", 4, \"\"]);
+d = handle({\"kind\": \"count\", \"value\": 21});
+e = handle(-1);
+f = handle(true);
+g = handle(\"hello\");
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        let get = |name: &str| scope.borrow().get_variable(&name.into());
+        assert_eq!(get("a"), Some(Object::String("naughty 3".into())));
+        assert_eq!(get("b"), Some(Object::String("rename 4 to Rudolph".into())));
+        assert_eq!(get("c"), Some(Object::String("empty name".into())));
+        assert_eq!(get("d"), Some(Object::Integer(42)));
+        assert_eq!(get("e"), Some(Object::String("minus one".into())));
+        assert_eq!(get("f"), Some(Object::String("yes".into())));
+        assert_eq!(get("g"), Some(Object::String("unknown hello".into())));
+    }
+
+    #[test]
+    fn test_match_2() {
+        let ast = parse_string_or_panic("
+count = 0;
+for value in [1, 2, 3, 4] {
+    match value % 2 {
+        0 => {
+            count = count + value;
+        }
+        _ => 0,
+    }
+}
+nothing = match 5 { 1 => 1 };
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"count".into()), Some(Object::Integer(6)));
+        assert_eq!(scope.borrow().get_variable(&"nothing".into()), Some(Object::None));
+
+        assert_eq!(
+            parse_string("[1, a] = [1, 2];"),
+            Err(SantaError::ParseError { cause: "Literals can only be used in the patterns of a match".into() })
+        );
+    }
+
+    #[test]
+    fn test_lambda_1() {
+        let ast = parse_string_or_panic("
+inc = x => x + 1;
+add = (a, b) => {
+    c = a + b;
+    yeet c * 2 back;
+};
+answer = () => 42;
+function apply(f, value) {
+    yeet f(value) back;
+}
+a = inc(1);
+b = add(2, 3);
+c = answer();
+d = apply(x => x * 10, 4);
+e = apply(([x, y]) => x - y, [9, 2]);
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        let get = |name: &str| scope.borrow().get_variable(&name.into());
+        assert_eq!(get("a"), Some(Object::Integer(2)));
+        assert_eq!(get("b"), Some(Object::Integer(10)));
+        assert_eq!(get("c"), Some(Object::Integer(42)));
+        assert_eq!(get("d"), Some(Object::Integer(40)));
+        assert_eq!(get("e"), Some(Object::Integer(7)));
+    }
+
+    #[test]
+    fn test_lambda_2() {
+        assert_eq!(
+            parse_string("f = x => x + 1;"),
+            parse_string("f = function (x) { yeet x + 1 back; };")
+        );
+
+        // Closures see the variables around them
+        let ast = parse_string_or_panic("
+step = 3;
+by_step = x => x * step;
+step = 4;
+a = by_step(2);
+b = match true { ready if ready => \"guarded\", _ => \"not\" };
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::Integer(8)));
+        assert_eq!(scope.borrow().get_variable(&"b".into()), Some(Object::String("guarded".into())));
+    }
+
+    #[test]
+    fn test_format_1() {
+        let ast = parse_string_or_panic("
+id = 4;
+name = \"Rudolph\";
+a = f\"id {id}: {name}\";
+b = f'{name:>10}|{name:*^11}|{3.14159:.2}|{id:3}|{id:<3}|';
+c = f\"{{braces}} {[1, 2][1]} {\"nested\"}\";
+d = 4 + \"x\";
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        let get = |name: &str| scope.borrow().get_variable(&name.into());
+        assert_eq!(get("a"), Some(Object::String("id 4: Rudolph".into())));
+        assert_eq!(get("b"), Some(Object::String("   Rudolph|**Rudolph**|3.14|  4|4  |".into())));
+        assert_eq!(get("c"), Some(Object::String("{braces} 2 nested".into())));
+        assert_eq!(get("d"), Some(Object::String("4x".into())));
+
+        assert_eq!(
+            parse_string("f\"{id:5x}\";"),
+            Err(SantaError::ParseError { cause: "Invalid format specification 5x".into() })
+        );
+
+        assert_eq!(
+            parse_string("f\"{id:.99999999999999}\";"),
+            Err(SantaError::ParseError { cause: "Format width or precision 99999999999999 is too large, it can be at most 1000".into() })
+        );
+    }
+
+    #[test]
+    fn test_format_2() {
+        let ast = parse_string_or_panic("
+a = format(\"{} and {}\", 1, \"two\");
+b = format(\"{1}{0}{1}\", \"o\", \"h\");
+c = format(\"{:^7.1}|{:.3}\", 2.25, \"Noël!\");
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        let get = |name: &str| scope.borrow().get_variable(&name.into());
+        assert_eq!(get("a"), Some(Object::String("1 and two".into())));
+        assert_eq!(get("b"), Some(Object::String("hoh".into())));
+        assert_eq!(get("c"), Some(Object::String("  2.2  |Noë".into())));
+
+        let ast = parse_string_or_panic("format(\"{} {}\", 1);");
+        assert_eq!(
+            eval_with_scope_err(ast, scope.clone()),
+            Err(SantaError::InvalidOperationError { cause: "There is no argument 1 for the format string".into() })
+        );
+
+        // Formatting to an absurd width is an error that can be caught, instead of running out of memory
+        let ast = parse_string_or_panic("format(\"{:99999999999999}\", 1);");
+        assert_eq!(
+            eval_with_scope_err(ast, scope.clone()),
+            Err(SantaError::InvalidOperationError {
+                cause: "Format width or precision 99999999999999 is too large, it can be at most 1000".into()
+            })
+        );
+
+        let ast = parse_string_or_panic("format(\"{\");");
+        assert_eq!(
+            eval_with_scope_err(ast, scope),
+            Err(SantaError::InvalidOperationError { cause: "Unclosed { in format string".into() })
+        );
+    }
+
+    #[test]
+    fn test_const_1() {
+        let ast = parse_string_or_panic("
+const REINDEER = 9;
+function count() {
+    let REINDEER = 8;
+    yeet REINDEER back;
+}
+a = count() + REINDEER;
+presents = [];
+const LIST = presents;
+LIST[0:0] = [1];
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::Integer(17)));
+
+        // The contents of a constant can still change, only the name is fixed
+        let ast = parse_string_or_panic("len(presents);");
+        assert_eq!(eval_with_scope(ast, scope.clone()), Object::Integer(1));
+
+        let error = |name: &str| Err(SantaError::InvalidOperationError {
+            cause: format!("{} is a constant and can't be assigned to", name),
+        });
+
+        let ast = parse_string_or_panic("REINDEER = 10;");
+        assert_eq!(eval_with_scope_err(ast, scope.clone()), error("REINDEER"));
+
+        let ast = parse_string_or_panic("REINDEER += 1;");
+        assert_eq!(eval_with_scope_err(ast, scope.clone()), error("REINDEER"));
+
+        let ast = parse_string_or_panic("function f() { global REINDEER; REINDEER = 1; } f();");
+        assert_eq!(eval_with_scope_err(ast, scope.clone()), error("REINDEER"));
+
+        let ast = parse_string_or_panic("let REINDEER = 1;");
+        assert_eq!(
+            eval_with_scope_err(ast, scope),
+            Err(SantaError::InvalidOperationError { cause: "REINDEER is a constant and can't be declared again".into() })
+        );
+    }
+
+    #[test]
+    fn test_const_2() {
+        let error = |name: &str| Err(SantaError::InvalidOperationError {
+            cause: format!("{} is a constant and can't be assigned to", name),
+        });
+
+        let scope = Scope::new();
+
+        let ast = parse_string_or_panic("print = 3;");
+        assert_eq!(eval_with_scope_err(ast, scope.clone()), error("print"));
+
+        let ast = parse_string_or_panic("SANTA_VERSION = 100;");
+        assert_eq!(eval_with_scope_err(ast, scope.clone()), error("SANTA_VERSION"));
+
+        let ast = parse_string_or_panic("function len(x) { yeet 0 back; }");
+        assert_eq!(eval_with_scope_err(ast, scope.clone()), error("len"));
+
+        let ast = parse_string_or_panic("[a, range] = [1, 2];");
+        assert_eq!(eval_with_scope_err(ast, scope.clone()), error("range"));
+
+        // Builtins can still be shadowed by a new variable
+        let ast = parse_string_or_panic("let print = 3; print;");
+        assert_eq!(eval_with_scope(ast, scope), Object::Integer(3));
+
+        // Shadowing a builtin only lasts until the end of the scope it was declared in
+        let ast = parse_string_or_panic("function f() { let len = 5; yeet len back; } [f(), len(\"ab\")];");
+        assert_eq!(eval_with_scope(ast, Scope::new()), Object::List(Rc::new(RefCell::new(vec![Object::Integer(5), Object::Integer(2)]))));
+    }
+
+    #[test]
+    fn test_const_3() {
+        // Declaring a builtin global shares the builtin, it doesn't replace it
+        let ast = parse_string_or_panic("
+function f() {
+    global len, SANTA_VERSION;
+    yeet [len(\"ab\"), SANTA_VERSION] back;
+}
+f() == [2, SANTA_VERSION];
+");
+        assert_eq!(eval_with_scope(ast, Scope::new()), Object::Boolean(true));
+
+        let ast = parse_string_or_panic("function f() { global SANTA_VERSION; SANTA_VERSION = 99; } f();");
+        assert_eq!(
+            eval_with_scope_err(ast, Scope::new()),
+            Err(SantaError::InvalidOperationError { cause: "SANTA_VERSION is a constant and can't be assigned to".into() })
+        );
+    }
+
+    #[test]
+    fn test_struct_1() {
+        let ast = parse_string_or_panic("
+struct Child { name, isnaughty, }
+a = Child(\"Rudolph\", false);
+b = Child(isnaughty: true, name: \"Grinch\");
+children = [a, b];
+children[0].isnaughty = true;
+b.name += \"!\";
+naughty = 0;
+for child in children {
+    if child.isnaughty {
+        naughty += 1;
+    }
+}
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"naughty".into()), Some(Object::Integer(2)));
+
+        let ast = parse_string_or_panic("b.name;");
+        assert_eq!(eval_with_scope(ast, scope.clone()), Object::String("Grinch!".into()));
+
+        let ast = parse_string_or_panic("a == Child(\"Rudolph\", true) and a != b;");
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_struct_2() {
+        let scope = Scope::new();
+        let ast = parse_string_or_panic("struct Child { name, isnaughty } c = Child(\"Rudolph\", false);");
+        eval_with_scope(ast, scope.clone());
+
+        let ast = parse_string_or_panic("c.age;");
+        assert_eq!(
+            eval_with_scope_err(ast, scope.clone()),
+            Err(SantaError::InvalidOperationError { cause: "Child has no field age".into() })
+        );
+
+        let ast = parse_string_or_panic("c.age = 3;");
+        assert_eq!(
+            eval_with_scope_err(ast, scope.clone()),
+            Err(SantaError::InvalidOperationError { cause: "Child has no field age".into() })
+        );
+
+        let ast = parse_string_or_panic("[1].length;");
+        assert_eq!(
+            eval_with_scope_err(ast, scope.clone()),
+            Err(SantaError::InvalidOperationError {
+                cause: "getting field length of List(RefCell { value: [Integer(1)] }) not supported, only structs have fields".into()
+            })
+        );
+
+        let ast = parse_string_or_panic("Child(\"Rudolph\");");
+        assert_eq!(
+            eval_with_scope_err(ast, scope),
+            Err(SantaError::InvalidOperationError { cause: "Not enough arguments for function".into() })
+        );
+
+        assert_eq!(
+            parse_string("struct Child { name, name }"),
+            Err(SantaError::ParseError { cause: "Field name is used more than once in struct Child".into() })
+        );
+    }
+
+    #[test]
+    fn test_struct_3() {
+        // Structs can change, so they can't be the key of a map
+        let scope = Scope::new();
+        let ast = parse_string_or_panic("struct P { x, y } m = {};");
+        eval_with_scope(ast, scope.clone());
+
+        let error = Err(SantaError::InvalidOperationError { cause: "P can't be used as the key of a map".into() });
+        for code in ["m[P(1, 2)] = 3;", "m[P(1, 2)];", "{P(1, 2): 3};"] {
+            let ast = parse_string_or_panic(code);
+            assert_eq!(eval_with_scope_err(ast, scope.clone()), error);
+        }
+    }
+
+    #[test]
+    fn test_method_1() {
+        let ast = parse_string_or_panic("
+res = [];
+for word in \"ho ho  HO\".split() {
+    res.push(word.lower());
+}
+list_push(res, \"ho\");
+last = res.pop();
+m = {\"a\": 1, \"b\": 2};
+total = 0;
+for key in m.keys() {
+    total += m[key];
+}
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(
+            scope.borrow().get_variable(&"res".into()),
+            Some(Object::List(Rc::new(RefCell::new(vec![Object::String("ho".into()); 3]))))
+        );
+        assert_eq!(scope.borrow().get_variable(&"last".into()), Some(Object::String("ho".into())));
+        assert_eq!(scope.borrow().get_variable(&"total".into()), Some(Object::Integer(3)));
+
+        let ast = parse_string_or_panic("\"{}/{}\".format(\"a,b\".split(\",\").len(), m.values().len()).upper();");
+        assert_eq!(eval_with_scope(ast, scope), Object::String("2/2".into()));
+    }
+
+    #[test]
+    fn test_method_2() {
+        let scope = Scope::new();
+
+        // A function in a field of a struct is called like a method
+        let ast = parse_string_or_panic("struct Elf { name, greet } e = Elf(\"Alabaster\", x => \"hi \" + x); e.greet(e.name);");
+        assert_eq!(eval_with_scope(ast, scope.clone()), Object::String("hi Alabaster".into()));
+
+        let ast = parse_string_or_panic("(3).upper();");
+        assert_eq!(
+            eval_with_scope_err(ast, scope.clone()),
+            Err(SantaError::InvalidOperationError { cause: "Integer has no method upper".into() })
+        );
+
+        let ast = parse_string_or_panic("[].pop();");
+        assert_eq!(eval_with_scope_err(ast, scope.clone()), Err(SantaError::IndexOutOfBounds));
+
+        let ast = parse_string_or_panic("\"abc\".split(\"\");");
+        assert_eq!(
+            eval_with_scope_err(ast, scope),
+            Err(SantaError::InvalidOperationError { cause: "split expects a string and optionally a separator that isn't empty".into() })
+        );
+    }
+
+    #[test]
+    fn test_operator_overloading_1() {
+        let ast = parse_string_or_panic("
+struct Vector {
+    x, y
+    function __add__(self, other) { yeet Vector(self.x + other.x, self.y + other.y) back; }
+    function __mul__(self, factor) { yeet Vector(self.x * factor, self.y * factor) back; }
+    function __rmul__(self, factor) { yeet self * factor back; }
+    function __neg__(self) { yeet Vector(-self.x, -self.y) back; }
+    function __eq__(self, other) { yeet self.x == other.x and self.y == other.y back; }
+    function __lt__(self, other) { yeet self.length() < other.length() back; }
+    function length(self) { yeet self.x * self.x + self.y * self.y back; }
+}
+a = Vector(1, 2);
+b = Vector(3, 4);
+sum = a + b;
+a += 2 * b;
+");
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        let ast = parse_string_or_panic("[sum.x, sum.y, a.x, a.y];");
+        let ints = |items: &[i64]| Object::List(Rc::new(RefCell::new(items.iter().map(|i| Object::Integer(*i)).collect())));
+        assert_eq!(eval_with_scope(ast, scope.clone()), ints(&[4, 6, 7, 10]));
+
+        let ast = parse_string_or_panic("[sum == Vector(4, 6), sum != Vector(4, 6), b < a, a > b, -b == Vector(-3, -4)];");
+        assert_eq!(
+            eval_with_scope(ast, scope),
+            Object::List(Rc::new(RefCell::new(vec![
+                Object::Boolean(true),
+                Object::Boolean(false),
+                Object::Boolean(true),
+                Object::Boolean(true),
+                Object::Boolean(true),
+            ])))
+        );
+    }
+
+    #[test]
+    fn test_operator_overloading_3() {
+        // Containers and match patterns compare their items with the hooks too
+        let ast = parse_string_or_panic("
+struct Never {
+    x
+    function __eq__(self, other) { yeet false back; }
+}
+struct Any {
+    x
+    function __eq__(self, other) { yeet true back; }
+}
+a = Never(1);
+matched = match Any(1) {
+    5 => true,
+    _ => false,
+};
+[a == a, [a] == [a], {\"a\": a} == {\"a\": a}, [Any(1)] == [Any(2)], matched];
+");
+        assert_eq!(
+            eval_with_scope(ast, Scope::new()),
+            Object::List(Rc::new(RefCell::new(vec![
+                Object::Boolean(false),
+                Object::Boolean(false),
+                Object::Boolean(false),
+                Object::Boolean(true),
+                Object::Boolean(true),
+            ])))
+        );
+    }
+
+    #[test]
+    fn test_operator_overloading_2() {
+        let scope = Scope::new();
+        let ast = parse_string_or_panic("
+struct Money {
+    cents
+    function __add__(self, other) { raise \"can't add \" + other; }
+}
+struct Elf { name }
+");
+        eval_with_scope(ast, scope.clone());
+
+        let ast = parse_string_or_panic("Money(1) + 2;");
+        assert_eq!(
+            eval_with_scope_err(ast, scope.clone()),
+            Err(SantaError::Raised { kind: "Error".into(), message: "can't add 2".into() })
+        );
+
+        // Without a hook, structs can only be compared like before
+        let ast = parse_string_or_panic("Elf(\"a\") == Elf(\"a\") and Elf(\"a\") != Elf(\"b\");");
+        assert_eq!(eval_with_scope(ast, scope.clone()), Object::Boolean(true));
+
+        let ast = parse_string_or_panic("Money(1).dollars();");
+        assert_eq!(
+            eval_with_scope_err(ast, scope),
+            Err(SantaError::InvalidOperationError { cause: "Money has no field or method dollars".into() })
+        );
+
+        assert_eq!(
+            parse_string("struct Money { cents function cents(self) { yeet 1 back; } }"),
+            Err(SantaError::ParseError { cause: "cents is defined more than once in struct Money".into() })
+        );
+        assert_eq!(
+            parse_string("struct Money { function (self) { yeet 1 back; } }"),
+            Err(SantaError::ParseError { cause: "The methods of struct Money need a name".into() })
+        );
+    }
+
+    #[test]
+    fn test_recursion_1() {
+        // Recursion is only limited by the size of the stack
+        let result = run_with_stack(|| {
+            let ast = parse_string_or_panic("function f(n) { if n == 0 { 0; } else { yeet f(n - 1) + 1 back; } } f(300);");
+            eval_with_scope(ast, Scope::new()) == Object::Integer(300)
+        });
+        assert!(result);
+    }
+
+    #[test]
+    fn test_recursion_2() {
+        // Endless recursion raises an error instead of crashing the interpreter
+        let result = run_with_stack(|| {
+            let ast = parse_string_or_panic("function f(n) { yeet f(n + 1) back; } f(0);");
+            eval_with_scope_err(ast, Scope::new()) == Err(SantaError::RecursionError)
+        });
+        assert!(result);
+    }
+
+    #[test]
+    fn test_comparison_1() {
+        let ast = parse_string_or_panic("3==5;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(false));
+    }
+
+    #[test]
+    fn test_comparison_2() {
+        let ast = parse_string_or_panic("3==3;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_comparison_3() {
+        let ast = parse_string_or_panic("3==3.0;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_comparison_4() {
+        let ast = parse_string_or_panic("3.0==3;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_comparison_5() {
+        let ast = parse_string_or_panic("3 > 3;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(false));
+    }
+
+    #[test]
+    fn test_comparison_6() {
+        let ast = parse_string_or_panic("3 < 3;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(false));
+    }
+
+    #[test]
+    fn test_comparison_8() {
+        let ast = parse_string_or_panic("3 >= 3;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_comparison_9() {
+        let ast = parse_string_or_panic("3 <= 3;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_comparison_10() {
+        let ast = parse_string_or_panic("4 > 3;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_comparison_11() {
+        let ast = parse_string_or_panic("3 < 4;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_comparison_12() {
+        let ast = parse_string_or_panic("3 != 4;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_comparison_13() {
+        let ast = parse_string_or_panic("3 != 3;");
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Boolean(false));
+    }
+
+    #[test]
+    fn test_logical_1() {
+        let ast = parse_string_or_panic("a = not 3 < 4 or 1 == 1 and false;");
+
+        assert_eq!(
+            ast,
+            vec![Box::new(Assignment {
+                name: Box::new(Name("a".into())),
+                expression: Box::new(Expression(Operator::Binary {
+                    operator: BinaryOperator::Or,
+                    lhs: Box::new(Expression(Operator::Unary {
+                        operator: UnaryOperator::Not,
+                        expr: Box::new(Expression(Operator::Binary {
+                            operator: BinaryOperator::Less,
+                            lhs: Box::new(Integer(3)),
+                            rhs: Box::new(Integer(4)),
+                        })),
+                    })),
+                    rhs: Box::new(Expression(Operator::Binary {
+                        operator: BinaryOperator::And,
+                        lhs: Box::new(Expression(Operator::Binary {
+                            operator: BinaryOperator::Equals,
+                            lhs: Box::new(Integer(1)),
+                            rhs: Box::new(Integer(1)),
+                        })),
+                        rhs: Box::new(AstNode::Boolean(false)),
+                    })),
+                })),
+                indexes: vec![]
+            })]
+        );
+
+        let scope = Scope::new();
+
+        assert_eq!(
+            eval_node(ast.into_iter().next().unwrap().as_ref(), scope.clone()).unwrap(),
+            Object::Boolean(false)
+        );
+    }
+
+    #[test]
+    fn test_logical_2() {
+        let ast = parse_string_or_panic(
+            "
+assert(true and true);
+assert(not (true and false));
+assert(true or false);
+assert(false or true);
+assert(not false);
+assert(not not true);
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Ok(Object::None));
+    }
+
+    #[test]
+    fn test_logical_3() {
+        let ast = parse_string_or_panic(
+            "
+a = false and undefined;
+b = true or undefined();
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope.clone()), Ok(Object::Boolean(true)));
+
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::Boolean(false)));
+    }
+
+    #[test]
+    fn test_logical_4() {
+        let ast = parse_string_or_panic(
+            "
+notice = 1;
+order = 2;
+android = notice + order;
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Ok(Object::Integer(3)));
+    }
+
+    #[test]
+    fn test_logical_5() {
+        let ast = parse_string_or_panic(
+            "
+a = true and 1;
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::InvalidOperationError {cause: "Operand of and not a boolean.".into()}));
+    }
+
+    #[test]
+    fn test_while_1() {
+        let ast = parse_string_or_panic(
+            "
+a = 0;
+while a < 10 {
+    a = a + 1;
+}
+
+            ",
+        );
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::Integer(10)));
+    }
+
+    #[test]
+    fn test_for_1() {
+        let ast = parse_string_or_panic(
+            "
+total = 0;
+for i in [1, 2, 3, 4] {
+    total = total + i;
+}
+
+            ",
+        );
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        assert_eq!(scope.borrow().get_variable(&"total".into()), Some(Object::Integer(10)));
+        assert_eq!(scope.borrow().get_variable(&"i".into()), None);
+    }
+
+    #[test]
+    fn test_for_2() {
+        let ast = parse_string_or_panic(
+            "
+res = \"\";
+for c in \"yeet\" {
+    res = c + res;
+}
+
+            ",
+        );
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        assert_eq!(scope.borrow().get_variable(&"res".into()), Some(Object::String("teey".into())));
+    }
+
+    #[test]
+    fn test_for_3() {
+        let ast = parse_string_or_panic(
+            "
+a = {1: 2, 3: 4, 5: 6};
+total = 0;
+for key in a {
+    total = total + key * 10 + a[key];
+}
+
+            ",
+        );
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        assert_eq!(scope.borrow().get_variable(&"total".into()), Some(Object::Integer(102)));
+    }
+
+    #[test]
+    fn test_for_4() {
+        let ast = parse_string_or_panic(
+            "
+res = [];
+for i in range(10, 0, -3) {
+    list_push(res, i);
+}
+
+assert(len(range(10, 0, -3)) == 4);
+assert(len(range(5)) == 5);
+assert(len(range(5, 2)) == 0);
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope.clone()), Ok(Object::None));
+
+        assert_eq!(scope.borrow().get_variable(&"res".into()), Some(Object::List(Rc::new(RefCell::new(vec![
+            Object::Integer(10),
+            Object::Integer(7),
+            Object::Integer(4),
+            Object::Integer(1),
+        ])))));
+    }
+
+    #[test]
+    fn test_for_5() {
+        let ast = parse_string_or_panic(
+            "
+i = 42;
+for i in range(3) {
+    print(i);
+}
+
+            ",
+        );
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        assert_eq!(scope.borrow().get_variable(&"i".into()), Some(Object::Integer(42)));
+    }
+
+    #[test]
+    fn test_for_6() {
+        let ast = parse_string_or_panic(
+            "
+for i in range(1, 2, 0) {
+}
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::InvalidOperationError {cause: "The step of a range can't be zero".into()}));
+    }
+
+    #[test]
+    fn test_for_7() {
+        // `for` and `in` are only keywords on their own, not at the start of a name
+        assert!(parse_string("forx in [1, 2] { print(x); }").is_err());
+        assert!(parse_string("for x inlist { print(x); }").is_err());
+
+        let ast = parse_string_or_panic(
+            "
+forx = 1;
+index = [2, 3];
+infant = 4;
+for i in index {
+    forx = forx + i + infant;
+}
+            ",
+        );
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+        assert_eq!(scope.borrow().get_variable(&"forx".into()), Some(Object::Integer(14)));
+    }
+
+    #[test]
+    fn test_break_1() {
+        let ast = parse_string_or_panic(
+            "
+a = 0;
+while true {
+    if a == 5 {
+        break;
+    }
+    a = a + 1;
+}
+
+            ",
+        );
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        assert_eq!(scope.borrow().get_variable(&"a".into()), Some(Object::Integer(5)));
+    }
+
+    #[test]
+    fn test_break_2() {
+        let ast = parse_string_or_panic(
+            "
+total = 0;
+for i in range(100) {
+    for j in range(100) {
+        if j == 2 {
+            break;
+        }
+        total = total + 1;
+    }
+}
+
+            ",
+        );
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        assert_eq!(scope.borrow().get_variable(&"total".into()), Some(Object::Integer(200)));
+    }
+
+    #[test]
+    fn test_break_3() {
+        let ast = parse_string_or_panic(
+            "
+break;
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::InvalidOperationError {cause: "break used outside of a loop".into()}));
+    }
+
+    #[test]
+    fn test_break_4() {
+        let ast = parse_string_or_panic(
+            "
+function a() {
+    break;
+}
+
+while true {
+    a();
+}
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Err(SantaError::InvalidOperationError {cause: "break used outside of a loop".into()}));
+    }
+
+    #[test]
+    fn test_continue_1() {
+        let ast = parse_string_or_panic(
+            "
+total = 0;
+for i in range(10) {
+    if i < 5 {
+        continue;
+    }
+    total = total + i;
+}
+
+            ",
+        );
+
+        let scope = Scope::new();
+        eval_with_scope(ast, scope.clone());
+
+        assert_eq!(scope.borrow().get_variable(&"total".into()), Some(Object::Integer(35)));
+    }
+
+    #[test]
+    fn test_continue_2() {
+        let ast = parse_string_or_panic(
+            "
+breakfast = 1;
+continued = breakfast + 1;
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope(ast, scope), Object::Integer(2));
+    }
+
+    #[test]
+    fn test_return_loop_1() {
+        let ast = parse_string_or_panic(
+            "
+function find(list, value) {
+    index = 0;
+    for i in list {
+        if i == value {
+            yeet index back;
+        }
+        index = index + 1;
+    }
+    yeet -1 back;
+}
+
+assert(find([4, 5, 6], 5) == 1);
+assert(find([4, 5, 6], 7) == -1);
+            ",
+        );
+
+        let scope = Scope::new();
+        assert_eq!(eval_with_scope_err(ast, scope), Ok(Object::None));
     }
 
     #[test]
@@ -139,6 +139,9 @@ a < b;
 You can compare floats and integers together. Watch out for floating point errors! Booleans act as the integers
 0 and 1 under comparison with an integer. Strings can be compared for equality only.
 
+Booleans can be combined with the high tech `and`, `or` and `not` operators. The right hand side of
+`and` and `or` is only evaluated when it's needed to know the answer.
+
 ### Conditionals
 
 Using the comparison operators, you can now build programs that conditionally execute code.
@@ -153,10 +156,38 @@ if a > b {{
 
 ```
 
+To choose between more than two options, chain them with `else if`:
+
+```
+if a > b {{
+    print(\"a greater than b\");
+}} else if a == b {{
+    print(\"a equal to b\");
+}} else {{
+    print(\"a less than b\");
+}}
+```
+
 If statements can be used as ternary operators by assigning them to a variable.
 The result of the last statement in the branch of the if statement that is executed
 will be yeeted back.
 
+To compare a value against a lot of options, use `match`. The first arm whose pattern fits the value is used,
+and just like an if statement, the match yeets back the result of that arm:
+
+```
+result = match message {{
+    [\"naughty\", id] => db_set(\"id\", id, \"isnaughty\", true),
+    [\"rename\", id, name] if len(name) > 0 => db_set(\"id\", id, \"name\", name),
+    {{kind: \"ping\"}} => \"pong\",
+    _ => \"something else\",
+}};
+```
+
+A pattern can be a value like `0` or `\"ping\"`, a name that gets the value, `_` for anything, or a list or map
+pattern that takes the value apart. The `if` after a pattern is a guard: the arm is only used when it is true.
+If no arm fits, the match yeets back nothing.
+
 {loops}",
 loops=match i {
     j if j >= LOOPS => "
@@ -173,6 +204,19 @@ while a < 1000 {
 }
 
 ```
+
+To visit every item of a list, every character of a string or every key of a map, use a `for` loop.
+The `range` function yeets back the integers from a start up to (but not including) a stop value, with an optional step.
+
+```
+
+for a in range(0, 1000) {
+    print(a);
+}
+
+```
+
+Use `break;` to stop a loop early, and `continue;` to skip to its next iteration.
 ",
             _ => ""
         }),
@@ -261,6 +305,8 @@ db_get_all() // yeets back a list of all records in the table. A record is a lis
 
 The santa language supports many common high tech syntax patterns such as
 variable assignment, addition (`+`), subtraction (`-`), multiplication (`*`) and division (`/`).
+Integer division (`//`), modulo (`%`), exponentiation (`**`) and the bitwise operators `&`, `|`, `^`, `<<` and `>>`
+are also available. Dividing by zero is an error.
 
 Each statement or expression is terminated with a semicolon (`;`).
 Below is an example of a simple Santa program.
@@ -271,6 +317,14 @@ b = 8;
 print(a + b); // prints 16. High tech!
 ```
 
+To change a variable using its old value, put the operator in front of the `=`. This also works for indexes,
+and the index is only worked out once:
+
+```
+a += 1; // the same as a = a + 1;
+presents[\"Noël\"] *= 2;
+```
+
 Suggestion: It's useful to try out examples given in this manual as they improve your understanding of the subject,
 which aid you in becoming our new engineer at E.L.F inc.
 
@@ -282,11 +336,12 @@ The print function accepts any number of arguments of any type, and will print t
 #### Data representation
 
 The Santa language is dynamically typed. Types are automatically converted. An example of this is division.
-Any number, under division, will be converted to a float.
+Any number, under division, will be converted to a float. Integer division (`//`) of two integers yeets back an integer.
 
 #### Comments
 
 Comments can be added to code by prefixing them with a double slash (`//`) or by enclosing the code with `/* ... */`
+A double slash that follows a value on the same line, like in `7 // 2`, is integer division and not a comment.
 
 #### Built in functions
 
@@ -299,6 +354,27 @@ print(len([1,2,3]));
 print(len(\"High Tech\"));
 ```
 
+The length of a string is the number of characters in it, so `len(\"Noël\")` is 4, just like the number of positions you can index.
+
+##### chars and bytes
+
+`chars(\"Noël\")` yeets back a list of the characters in a string. Some characters that look like one are really
+several, like an `e` followed by an accent. Use `chars(text, graphemes: true)` to keep those together.
+`bytes(text)` yeets back a list of the bytes (integers from 0 to 255) that make up the string.
+
+##### Methods
+
+Strings, lists and maps also have methods, which are called with a dot after the value:
+
+```
+children = [];
+children.push(\"Rudolph\");
+print(\"High Tech\".upper(), \"a,b\".split(\",\"), {{\"a\": 1}}.keys());
+```
+
+Strings have `len`, `chars`, `bytes`, `format`, `upper`, `lower` and `split`, lists have `len`, `push` and `pop`
+and maps have `len`, `keys` and `values`.
+
 {assertion}
 #### Datatypes
 
@@ -314,9 +390,22 @@ The datatypes available in the Santa language are:
 ##### Strings
 
 Strings can be created with single or double quotes surrounding any text.
+Special characters are written with escapes like `\\n` (newline), `\\t` (tab), `\\\\` (backslash), `\\\"` (quote)
+and `\\u{{1F385}}` (any unicode character). Strings surrounded by three quotes (`\"\"\"` or `'''`) can span multiple lines,
+and prefixing a string with `r` turns off escapes.
 Strings can be indexed by an integer and yeet back the character at that position. Characters are strings of length 1.
 An object, string or other, can be appended to a string by using the `+` operator.
 
+Prefixing a string with `f` fills in the expressions between braces. After a `:`, you can say how to format
+the value: a fill character and alignment (`<`, `>` or `^`), a width, and a precision (the number of decimals,
+or the maximum length of a string), both at most 1000. Use `{{{{` and `}}}}` for the braces themselves. The
+`format` function does the same with a template and values:
+
+```
+print(f\"id {{id}}: {{name:>10}} {{score:.2}}\");
+print(format(\"id {{}}: {{:>10}} {{:.2}}\", id, name, score));
+```
+
 ##### Maps
 
 A Map can be created by using the following syntax: `{{key: value, key: value, ...}}`. Keys can be any type apart from lists and maps themselves as they are mutable.
@@ -336,6 +425,22 @@ print(a[\"a\"]);
 A List is made using the following syntax: `[1,2,3,4]`. Indexing a List with an integer index yeets back the item at this index.
 Lists can be concatenated using the `+` operator and repeated using the `*` operator.
 
+Negative indices count from the end, so `a[-1]` is the last item. A part of a list or string is taken with
+`a[start:end]` or `a[start:end:step]`, where every part can be left out: `a[::-1]` is `a` backwards.
+Assigning to a part of a list like `a[1:3] = [7, 8, 9];` replaces that part.
+
+##### Taking lists and maps apart
+
+Lists and maps can be taken apart in one assignment. A `*rest` gets the items that are left over,
+and `{{name}}` is short for `{{name: name}}`:
+
+```
+[id, name, naughty] = db_get(\"id\", 3);
+[first, *others] = [1, 2, 3];
+{{name, isnaughty: naughty, **other_keys}} = record;
+```
+
+The same works for the variable of a for loop like `for [id, name] in pairs {{ ... }}` and for function parameters.
 
 ",
         boolean = if unsafe { MANUAL_ID } >= CONDITIONALS {"\n* Boolean"} else {""},
@@ -406,8 +511,86 @@ assert(sum() == 0);
 
 ```
 
+Parameters can have a default value, and arguments can be given by name. A last parameter prefixed with `**`
+collects the named arguments that don't match any parameter in a map:
+
+```
+function greet(name, greeting = \"Hello\", **options) {
+    yeet greeting + \" \" + name back;
+}
+
+assert(greet(\"Rudolph\") == \"Hello Rudolph\");
+assert(greet(\"Rudolph\", greeting: \"Hi\") == \"Hi Rudolph\");
+```
+
+To pass the items of a list as separate arguments, prefix it with `*` like `sum(*values)`. Prefixing a map with `**`
+passes its items as named arguments.
+
 Functions can be nested and form closures over their outer scope. High tech!
 
+Small functions can be written with an arrow. The value after the arrow is yeeted back, and with a block after
+the arrow it works just like `function`:
+
+```
+double = x => x * 2;
+add = (a, b) => a + b;
+register_network_handler(data => print(data));
+```
+
+Watch out: assigning to a variable changes the variable with that name outside the function if there is one.
+Declare variables with `let` to keep them inside the function, and use `global name;` or `nonlocal name;` to
+say that you really want to change a variable outside of it. After a `strict;` statement, assigning to a variable
+that was never declared is an error, and so is assigning to a variable outside the function without them.
+
+Variables declared with `const NAME = value;` can't be assigned to again. The built in functions and
+`SANTA_VERSION` are constants too, so you can't accidentally replace `print`. Declaring a new variable with
+`let` or `const` does shadow a constant from outside its own scope though: after `let print = 5;`, `print` is 5
+until the end of the block or function it was declared in, and at the top of your program that's until the end.
+
+To keep things together that belong together, define a struct. Calling the struct like a function creates one,
+with the arguments for its fields in order or by name:
+
+```
+struct Child { name, isnaughty }
+
+child = Child(\"Rudolph\", isnaughty: false);
+child.isnaughty = true;
+print(child.name);
+```
+
+After the fields, a struct can define methods. They get the struct they are called on as their first argument.
+Methods with names like `__add__` are used by operators, so you can add up money or vectors:
+
+```
+struct Money {
+    cents
+    function __add__(self, other) {
+        yeet Money(self.cents + other.cents) back;
+    }
+    function __lt__(self, other) {
+        yeet self.cents < other.cents back;
+    }
+    function euros(self) {
+        yeet self.cents / 100 back;
+    }
+}
+
+assert((Money(150) + Money(250)).euros() == 4);
+```
+
+The operators are `__add__`, `__sub__`, `__mul__`, `__div__`, `__floordiv__`, `__mod__`, `__pow__`, `__and__`,
+`__or__`, `__xor__`, `__lshift__`, `__rshift__`, `__neg__`, `__eq__`, `__ne__`, `__lt__`, `__gt__`, `__le__` and
+`__ge__`. When only the right hand side is a struct, like in `2 * vector`, its `__rmul__` (and so on) is used.
+A comparison like `a > b` falls back to `b < a`, and `a != b` to `not a == b`.
+
+Put functions you use a lot in a separate file, and import them. Paths are relative to the file that imports them,
+and every file is only run once, no matter how often it is imported.
+
+```
+import \"helpers.santa\";
+import split_string, string_to_integer from \"helpers.santa\";
+```
+
 "
         }
         _ => "",
@@ -498,6 +681,22 @@ register_network_handler(function (data){
 
 After you registered a network hadler, you can call `listen()` to stop and wait for incoming messages.
 
+Santa sometimes sends weird messages. To stop an error from ending your program, catch it:
+
+```
+
+try {
+    handle(data);
+} catch err {
+    print(err[\"kind\"], err[\"message\"]);
+} finally {
+    print(\"done\");
+}
+
+```
+
+You can raise your own errors with `raise \"message\";` (or `throw`). Raising a map with a `kind` and a `message` sets both.
+
 ");
     }
 
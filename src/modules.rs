@@ -0,0 +1,62 @@
+use crate::error::SantaError;
+use crate::eval::{eval_block_with_scope, flow_to_value, Scope};
+use crate::parser::parse_file;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+thread_local! {
+    /// Modules that were already imported, so every module is only evaluated once.
+    static MODULES: RefCell<HashMap<PathBuf, Rc<RefCell<Scope>>>> = RefCell::new(HashMap::new());
+    /// Modules that are being imported right now, innermost last, to find circular imports.
+    static LOADING: RefCell<Vec<PathBuf>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Yeets back the global scope of the module at `path`, evaluating the module if it wasn't imported yet.
+pub fn import_module(path: &str) -> Result<Rc<RefCell<Scope>>, SantaError> {
+    let canonical = fs::canonicalize(path).map_err(|e| SantaError::ImportError {
+        cause: format!("{} ({})", path, e),
+    })?;
+
+    if let Some(module) = MODULES.with(|i| i.borrow().get(&canonical).cloned()) {
+        return Ok(module);
+    }
+
+    if let Some(position) = LOADING.with(|i| i.borrow().iter().position(|i| *i == canonical)) {
+        let cycle = LOADING.with(|i| {
+            i.borrow()[position..]
+                .iter()
+                .chain(std::iter::once(&canonical))
+                .map(|i| i.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        });
+
+        return Err(SantaError::ImportError {
+            cause: format!("circular import {}", cycle),
+        });
+    }
+
+    LOADING.with(|i| i.borrow_mut().push(canonical.clone()));
+    let module = load_module(path);
+    LOADING.with(|i| i.borrow_mut().pop());
+
+    let module = module?;
+    MODULES.with(|i| i.borrow_mut().insert(canonical, module.clone()));
+
+    Ok(module)
+}
+
+fn load_module(path: &str) -> Result<Rc<RefCell<Scope>>, SantaError> {
+    let text = fs::read_to_string(path).map_err(|e| SantaError::ImportError {
+        cause: format!("{} ({})", path, e),
+    })?;
+
+    let ast = parse_file(path, &text)?;
+    let module = Scope::new();
+    flow_to_value(eval_block_with_scope(&ast, module.clone())?)?;
+
+    Ok(module)
+}
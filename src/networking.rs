@@ -89,9 +89,10 @@ fn builtin_network_listen(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaErro
 
                     local_db.set_first("id".into(), Object::Integer(record as i64), "isnaughty".into(), Object::Boolean(value));
 
+                    // Errors raised by the handler get listen in their traceback, since it called the handler
                     func.call(ArgumentList::new(vec![
                         Object::String(format!("update id {}; set isnaughty=<{}>", record, value)),
-                    ]))?;
+                    ])).map_err(|e| e.in_function("listen"))?;
                 },
                 1 => {
                     let numnames = NAMES.len();
@@ -100,7 +101,7 @@ fn builtin_network_listen(scope: Rc<RefCell<Scope>>) -> Result<Object, SantaErro
 
                     func.call(ArgumentList::new(vec![
                         Object::String(format!("update id {}; set name=<{}>", record, value)),
-                    ]))?;
+                    ])).map_err(|e| e.in_function("listen"))?;
                 },
                 _ => unimplemented!(),
             }
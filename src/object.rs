@@ -2,10 +2,21 @@ use crate::error::SantaError;
 use crate::function::{ArgumentList, Function};
 use std::fmt::{Display, Error, Formatter};
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 use std::cell::RefCell;
 
+/// A type defined with `struct Name { field, ... }`.
+#[derive(Debug, PartialEq)]
+pub struct StructType {
+    pub name: String,
+    pub fields: Vec<String>,
+    /// The methods defined in the struct, which get the struct they are called on as their first argument.
+    pub methods: HashMap<String, Function>,
+}
+
 #[derive(Debug, Clone)]
 pub enum Object {
     Integer(i64),
@@ -15,6 +26,11 @@ pub enum Object {
     Boolean(bool),
     List(Rc<RefCell<Vec<Object>>>),
     Map(Rc<RefCell<HashMap<Object, Object>>>),
+    Range(i64, i64, i64),
+    /// The part of a list or string to take with `a[start:end:step]`, where every bound is optional.
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    /// An instance of a struct, with the values of its fields in the order of the definition.
+    Struct(Rc<StructType>, Rc<RefCell<Vec<Object>>>),
     None,
 }
 
@@ -29,6 +45,9 @@ impl Hash for Object {
             Self::Boolean(i) => i.hash(state),
             Self::List(_) => unimplemented!("Lists are not a hashable type!"),
             Self::Map(_) => unimplemented!("Maps are not a hashable type!"),
+            Self::Struct(..) => unimplemented!("Structs are not a hashable type!"),
+            Self::Range(start, stop, step) => (start, stop, step).hash(state),
+            Self::Slice(start, end, step) => (start, end, step).hash(state),
         }
     }
 }
@@ -64,12 +83,56 @@ impl Display for Object {
             Self::Map(map) => write!(f, "{:?}", map.borrow().iter().map(|(i, j)| {
                 format!("{}:{}", i, j)
             }).collect::<Vec<String>>()),
-
+            Self::Range(start, stop, step) => write!(f, "range({}, {}, {})", start, stop, step),
+            Self::Slice(start, end, step) => {
+                let bound = |i: &Option<i64>| i.map_or("None".to_string(), |i| i.to_string());
+                write!(f, "slice({}, {}, {})", bound(start), bound(end), bound(step))
+            }
+            Self::Struct(structtype, values) => write!(
+                f,
+                "{}({})",
+                structtype.name,
+                structtype
+                    .fields
+                    .iter()
+                    .zip(values.borrow().iter())
+                    .map(|(field, value)| format!("{}: {}", field, value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
         }
     }
 }
 
 impl Object {
+    /// The name of the type of this value as a santa program sees it, which is the name of the struct for structs.
+    pub fn type_name(&self) -> &str {
+        match self {
+            Self::Integer(_) => "Integer",
+            Self::Float(_) => "Float",
+            Self::String(_) => "String",
+            Self::Boolean(_) => "Boolean",
+            Self::Function(_) => "Function",
+            Self::List(_) => "List",
+            Self::Map(_) => "Map",
+            Self::Range(..) => "Range",
+            Self::Slice(..) => "Slice",
+            Self::Struct(structtype, _) => &structtype.name,
+            Self::None => "None",
+        }
+    }
+
+    /// Checks that this value can be the key of a map. Values that can change, like lists and
+    /// structs, can't be hashed.
+    pub fn check_hashable(&self) -> Result<(), SantaError> {
+        match self {
+            Self::Function(_) | Self::List(_) | Self::Map(_) | Self::Struct(..) => Err(SantaError::InvalidOperationError {
+                cause: format!("{} can't be used as the key of a map", self.type_name()),
+            }),
+            _ => Ok(()),
+        }
+    }
+
     pub fn call(&self, arglist: ArgumentList) -> Result<Object, SantaError> {
         match self {
             Self::Function(i) => i.call(arglist),
@@ -79,9 +142,32 @@ impl Object {
         }
     }
 
+    /// Yeets back the method `name` of a struct, which doesn't include functions stored in its fields.
+    fn method(&self, name: &str) -> Option<Function> {
+        match self {
+            Self::Struct(structtype, _) => structtype.methods.get(name).cloned(),
+            _ => None,
+        }
+    }
+
+    /// Calls the method of a struct that overloads an operator, like `__add__` for `+`. When this value
+    /// doesn't overload the operator, `reflected` is tried on the other side, like `2 * v` calling
+    /// `v.__rmul__(2)`. Comparisons are mirrored instead: `a < b` is the same as `b > a`.
+    fn operator_hook(&self, other: &Object, hook: &str, reflected: &str) -> Option<Result<Object, SantaError>> {
+        if let Some(method) = self.method(hook) {
+            return Some(method.call(ArgumentList::new(vec![self.clone(), other.clone()])));
+        }
+
+        other.method(reflected).map(|method| method.call(ArgumentList::new(vec![other.clone(), self.clone()])))
+    }
+
     pub fn add(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__add__", "__radd__") {
+            return result;
+        }
+
         match (self, other) {
-            (Self::Integer(i), Self::Integer(j)) => Ok(Self::Integer(i + j)),
+            (Self::Integer(i), Self::Integer(j)) => Ok(Self::Integer(i.checked_add(*j).ok_or(SantaError::IntegerOverflow)?)),
 
             (Self::Integer(i), Self::Float(j)) => Ok(Self::Float(*i as f64 + j)),
             (Self::Float(i), Self::Integer(j)) => Ok(Self::Float(i + *j as f64)),
@@ -97,7 +183,7 @@ impl Object {
             },
 
             (Self::String(string), other) => Ok(Self::String(format!("{}{}", string, other))),
-            (other, Self::String(string)) => Ok(Self::String(format!("{}{}", string, other))),
+            (other, Self::String(string)) => Ok(Self::String(format!("{}{}", other, string))),
 
             _ => Err(SantaError::InvalidOperationError {
                 cause: format!("addition between {:?} and {:?} not supported", self, other),
@@ -106,8 +192,12 @@ impl Object {
     }
 
     pub fn subtract(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__sub__", "__rsub__") {
+            return result;
+        }
+
         match (self, other) {
-            (Self::Integer(i), Self::Integer(j)) => Ok(Self::Integer(i - j)),
+            (Self::Integer(i), Self::Integer(j)) => Ok(Self::Integer(i.checked_sub(*j).ok_or(SantaError::IntegerOverflow)?)),
 
             (Self::Integer(i), Self::Float(j)) => Ok(Self::Float(*i as f64 - j)),
             (Self::Float(i), Self::Integer(j)) => Ok(Self::Float(i - *j as f64)),
@@ -127,8 +217,12 @@ impl Object {
     }
 
     pub fn multiply(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__mul__", "__rmul__") {
+            return result;
+        }
+
         match (self, other) {
-            (Self::Integer(i), Self::Integer(j)) => Ok(Self::Integer(i * j)),
+            (Self::Integer(i), Self::Integer(j)) => Ok(Self::Integer(i.checked_mul(*j).ok_or(SantaError::IntegerOverflow)?)),
 
             (Self::Integer(i), Self::Float(j)) => Ok(Self::Float(*i as f64 * j)),
             (Self::Float(i), Self::Integer(j)) => Ok(Self::Float(i * *j as f64)),
@@ -138,10 +232,16 @@ impl Object {
             (Self::Boolean(i), other) => Self::Integer(*i as i64).multiply(other),
             (other, Self::Boolean(i)) => other.multiply(&Self::Integer(*i as i64)),
 
-            (Self::String(string), Self::Integer(i)) => Ok(Self::String(string.repeat(*i as usize))),
+            // Repeating something a negative number of times yields nothing
+            (Self::String(string), Self::Integer(i)) => {
+                let count = (*i).max(0) as usize;
+                string.len().checked_mul(count).ok_or(SantaError::IntegerOverflow)?;
+                Ok(Self::String(string.repeat(count)))
+            },
             (Self::List(lst), Self::Integer(i)) => {
+                let length = lst.borrow().len().checked_mul((*i).max(0) as usize).ok_or(SantaError::IntegerOverflow)?;
                 Ok(Self::List(Rc::new(RefCell::new(
-                        lst.borrow().iter().cloned().cycle().take(lst.borrow().len() * *i as usize).collect()
+                        lst.borrow().iter().cloned().cycle().take(length).collect()
                 ))))
             },
 
@@ -154,8 +254,22 @@ impl Object {
         }
     }
 
+    fn is_zero(&self) -> bool {
+        match self {
+            Self::Integer(i) => *i == 0,
+            Self::Float(i) => *i == 0.0,
+            _ => false,
+        }
+    }
+
     pub fn divide(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__div__", "__rdiv__") {
+            return result;
+        }
+
         match (self, other) {
+            (Self::Integer(_), j) | (Self::Float(_), j) if j.is_zero() => Err(SantaError::DivisionByZero),
+
             (Self::Integer(i), Self::Integer(j)) => Ok(Self::Float(*i as f64 / *j as f64)),
 
             (Self::Integer(i), Self::Float(j)) => Ok(Self::Float(*i as f64 / *j as f64)),
@@ -172,9 +286,221 @@ impl Object {
         }
     }
 
+    pub fn floordivide(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__floordiv__", "__rfloordiv__") {
+            return result;
+        }
+
+        match (self, other) {
+            (Self::Integer(_), j) | (Self::Float(_), j) if j.is_zero() => Err(SantaError::DivisionByZero),
+
+            (Self::Integer(i), Self::Integer(j)) => {
+                let quotient = i.checked_div(*j).ok_or(SantaError::IntegerOverflow)?;
+                let remainder = i.wrapping_rem(*j);
+                // Rust rounds towards zero, we round down
+                if remainder != 0 && (remainder < 0) != (*j < 0) {
+                    Ok(Self::Integer(quotient - 1))
+                } else {
+                    Ok(Self::Integer(quotient))
+                }
+            },
+
+            (Self::Integer(i), Self::Float(j)) => Ok(Self::Float((*i as f64 / j).floor())),
+            (Self::Float(i), Self::Integer(j)) => Ok(Self::Float((i / *j as f64).floor())),
+
+            (Self::Float(i), Self::Float(j)) => Ok(Self::Float((i / j).floor())),
+
+            (Self::Boolean(i), other) => Self::Integer(*i as i64).floordivide(other),
+            (other, Self::Boolean(i)) => other.floordivide(&Self::Integer(*i as i64)),
+
+            _ => Err(SantaError::InvalidOperationError {
+                cause: format!("integer division between {:?} and {:?} not supported", self, other),
+            }),
+        }
+    }
+
+    pub fn modulo(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__mod__", "__rmod__") {
+            return result;
+        }
+
+        match (self, other) {
+            (Self::Integer(_), j) | (Self::Float(_), j) if j.is_zero() => Err(SantaError::DivisionByZero),
+
+            (Self::Integer(i), Self::Integer(j)) => {
+                let remainder = i.wrapping_rem(*j);
+                // The result has the sign of the divisor, to match integer division rounding down
+                if remainder != 0 && (remainder < 0) != (*j < 0) {
+                    Ok(Self::Integer(remainder + j))
+                } else {
+                    Ok(Self::Integer(remainder))
+                }
+            },
+
+            (Self::Integer(i), Self::Float(j)) => Self::Float(*i as f64).modulo(&Self::Float(*j)),
+            (Self::Float(i), Self::Integer(j)) => Self::Float(*i).modulo(&Self::Float(*j as f64)),
+
+            (Self::Float(i), Self::Float(j)) => {
+                let remainder = i % j;
+                if remainder != 0.0 && (remainder < 0.0) != (*j < 0.0) {
+                    Ok(Self::Float(remainder + j))
+                } else {
+                    Ok(Self::Float(remainder))
+                }
+            },
+
+            (Self::Boolean(i), other) => Self::Integer(*i as i64).modulo(other),
+            (other, Self::Boolean(i)) => other.modulo(&Self::Integer(*i as i64)),
+
+            _ => Err(SantaError::InvalidOperationError {
+                cause: format!("modulo between {:?} and {:?} not supported", self, other),
+            }),
+        }
+    }
+
+    pub fn power(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__pow__", "__rpow__") {
+            return result;
+        }
+
+        match (self, other) {
+            (i, Self::Integer(j)) if i.is_zero() && *j < 0 => Err(SantaError::DivisionByZero),
+            (i, Self::Float(j)) if i.is_zero() && *j < 0.0 => Err(SantaError::DivisionByZero),
+
+            (Self::Integer(i), Self::Integer(j)) if *j >= 0 => {
+                let exponent = match u32::try_from(*j) {
+                    Ok(exponent) => exponent,
+                    // Only -1, 0 and 1 can be raised this far without overflowing,
+                    // and for those only whether the exponent is odd matters
+                    Err(_) if !(-1..=1).contains(i) => return Err(SantaError::IntegerOverflow),
+                    Err(_) => 2 + (*j % 2) as u32,
+                };
+                Ok(Self::Integer(i.checked_pow(exponent).ok_or(SantaError::IntegerOverflow)?))
+            },
+            (Self::Integer(i), Self::Integer(j)) => Ok(Self::Float((*i as f64).powf(*j as f64))),
+
+            (Self::Integer(i), Self::Float(j)) => Ok(Self::Float((*i as f64).powf(*j))),
+            (Self::Float(i), Self::Integer(j)) => Ok(Self::Float(i.powf(*j as f64))),
+
+            (Self::Float(i), Self::Float(j)) => Ok(Self::Float(i.powf(*j))),
+
+            (Self::Boolean(i), other) => Self::Integer(*i as i64).power(other),
+            (other, Self::Boolean(i)) => other.power(&Self::Integer(*i as i64)),
+
+            _ => Err(SantaError::InvalidOperationError {
+                cause: format!("exponentiation between {:?} and {:?} not supported", self, other),
+            }),
+        }
+    }
+
+    pub fn bitand(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__and__", "__rand__") {
+            return result;
+        }
+
+        match (self, other) {
+            (Self::Integer(i), Self::Integer(j)) => Ok(Self::Integer(i & j)),
+            (Self::Boolean(i), Self::Boolean(j)) => Ok(Self::Boolean(i & j)),
+
+            (Self::Boolean(i), other) => Self::Integer(*i as i64).bitand(other),
+            (other, Self::Boolean(i)) => other.bitand(&Self::Integer(*i as i64)),
+
+            _ => Err(SantaError::InvalidOperationError {
+                cause: format!("bitwise and between {:?} and {:?} not supported", self, other),
+            }),
+        }
+    }
+
+    pub fn bitor(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__or__", "__ror__") {
+            return result;
+        }
+
+        match (self, other) {
+            (Self::Integer(i), Self::Integer(j)) => Ok(Self::Integer(i | j)),
+            (Self::Boolean(i), Self::Boolean(j)) => Ok(Self::Boolean(i | j)),
+
+            (Self::Boolean(i), other) => Self::Integer(*i as i64).bitor(other),
+            (other, Self::Boolean(i)) => other.bitor(&Self::Integer(*i as i64)),
+
+            _ => Err(SantaError::InvalidOperationError {
+                cause: format!("bitwise or between {:?} and {:?} not supported", self, other),
+            }),
+        }
+    }
+
+    pub fn bitxor(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__xor__", "__rxor__") {
+            return result;
+        }
+
+        match (self, other) {
+            (Self::Integer(i), Self::Integer(j)) => Ok(Self::Integer(i ^ j)),
+            (Self::Boolean(i), Self::Boolean(j)) => Ok(Self::Boolean(i ^ j)),
+
+            (Self::Boolean(i), other) => Self::Integer(*i as i64).bitxor(other),
+            (other, Self::Boolean(i)) => other.bitxor(&Self::Integer(*i as i64)),
+
+            _ => Err(SantaError::InvalidOperationError {
+                cause: format!("bitwise xor between {:?} and {:?} not supported", self, other),
+            }),
+        }
+    }
+
+    pub fn shiftleft(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__lshift__", "__rlshift__") {
+            return result;
+        }
+
+        match (self, other) {
+            (Self::Integer(i), Self::Integer(j)) if (0..64).contains(j) => {
+                // Bits shifted out of the integer would silently change its value
+                if (i << j) >> j == *i {
+                    Ok(Self::Integer(i << j))
+                } else {
+                    Err(SantaError::IntegerOverflow)
+                }
+            },
+            (Self::Integer(_), Self::Integer(j)) => Err(SantaError::InvalidOperationError {
+                cause: format!("shift by {} out of range", j),
+            }),
+
+            (Self::Boolean(i), other) => Self::Integer(*i as i64).shiftleft(other),
+            (other, Self::Boolean(i)) => other.shiftleft(&Self::Integer(*i as i64)),
+
+            _ => Err(SantaError::InvalidOperationError {
+                cause: format!("shifting {:?} by {:?} not supported", self, other),
+            }),
+        }
+    }
+
+    pub fn shiftright(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__rshift__", "__rrshift__") {
+            return result;
+        }
+
+        match (self, other) {
+            (Self::Integer(i), Self::Integer(j)) if (0..64).contains(j) => Ok(Self::Integer(i >> j)),
+            (Self::Integer(_), Self::Integer(j)) => Err(SantaError::InvalidOperationError {
+                cause: format!("shift by {} out of range", j),
+            }),
+
+            (Self::Boolean(i), other) => Self::Integer(*i as i64).shiftright(other),
+            (other, Self::Boolean(i)) => other.shiftright(&Self::Integer(*i as i64)),
+
+            _ => Err(SantaError::InvalidOperationError {
+                cause: format!("shifting {:?} by {:?} not supported", self, other),
+            }),
+        }
+    }
+
     pub fn negate(&self) -> Result<Object, SantaError> {
+        if let Some(method) = self.method("__neg__") {
+            return method.call(ArgumentList::new(vec![self.clone()]));
+        }
+
         match self {
-            Self::Integer(i) => Ok(Self::Integer(-i)),
+            Self::Integer(i) => Ok(Self::Integer(i.checked_neg().ok_or(SantaError::IntegerOverflow)?)),
             Self::Float(i) => Ok(Self::Float(-i)),
 
             Self::Boolean(i) => Ok(Self::Boolean(!*i)),
@@ -185,7 +511,21 @@ impl Object {
         }
     }
 
+    pub fn not(&self) -> Result<Object, SantaError> {
+        match self {
+            Self::Boolean(i) => Ok(Self::Boolean(!*i)),
+
+            _ => Err(SantaError::InvalidOperationError {
+                cause: format!("logical not for {:?} not supported", self),
+            }),
+        }
+    }
+
     pub fn equals(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__eq__", "__eq__") {
+            return result;
+        }
+
         match (self, other) {
             (Self::Integer(i), Self::Integer(j)) => Ok(Self::Boolean(*i == *j)),
 
@@ -197,15 +537,18 @@ impl Object {
             (Self::Boolean(i), Self::Boolean(j)) => Ok(Self::Boolean(*i == *j)),
             (Self::String(i), Self::String(j)) => Ok(Self::Boolean(*i == *j)),
 
-            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(*i == (*j as f64))),
-            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean((*i as f64) == *j)),
+            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(compare_integer_float(*j, *i) == Some(Ordering::Equal))),
+            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean(compare_integer_float(*i, *j) == Some(Ordering::Equal))),
 
             (Self::List(i), Self::List(j)) => Ok(Self::Boolean(i == j)),
             (Self::Map(i), Self::Map(j)) => Ok(Self::Boolean(i == j)),
+            (Self::Range(a, b, c), Self::Range(i, j, k)) => Ok(Self::Boolean((a, b, c) == (i, j, k))),
+            (Self::Slice(a, b, c), Self::Slice(i, j, k)) => Ok(Self::Boolean((a, b, c) == (i, j, k))),
 
             (Self::None, Self::None) => Ok(Self::Boolean(true)),
 
             (Self::Function(i), Self::Function(j)) => Ok(Self::Boolean(i == j)),
+            (Self::Struct(a, i), Self::Struct(b, j)) => Ok(Self::Boolean(Rc::ptr_eq(a, b) && i == j)),
 
             _ => Err(SantaError::InvalidOperationError {
                 cause: format!(
@@ -217,6 +560,14 @@ impl Object {
     }
 
     pub fn notequals(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__ne__", "__ne__") {
+            return result;
+        }
+        // Structs that only overload `==` get `!=` for free
+        if self.method("__eq__").is_some() || other.method("__eq__").is_some() {
+            return self.equals(other)?.not();
+        }
+
         match (self, other) {
             (Self::Integer(i), Self::Integer(j)) => Ok(Self::Boolean(*i != *j)),
 
@@ -228,21 +579,28 @@ impl Object {
             (Self::Boolean(i), Self::Boolean(j)) => Ok(Self::Boolean(*i != *j)),
             (Self::String(i), Self::String(j)) => Ok(Self::Boolean(*i != *j)),
 
-            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(*i != (*j as f64))),
-            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean((*i as f64) != *j)),
+            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(compare_integer_float(*j, *i) != Some(Ordering::Equal))),
+            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean(compare_integer_float(*i, *j) != Some(Ordering::Equal))),
 
             (Self::List(i), Self::List(j)) => Ok(Self::Boolean(i != j)),
             (Self::Map(i), Self::Map(j)) => Ok(Self::Boolean(i != j)),
+            (Self::Range(a, b, c), Self::Range(i, j, k)) => Ok(Self::Boolean((a, b, c) != (i, j, k))),
+            (Self::Slice(a, b, c), Self::Slice(i, j, k)) => Ok(Self::Boolean((a, b, c) != (i, j, k))),
 
             (Self::None, Self::None) => Ok(Self::Boolean(true)),
 
             (Self::Function(i), Self::Function(j)) => Ok(Self::Boolean(i != j)),
+            (Self::Struct(a, i), Self::Struct(b, j)) => Ok(Self::Boolean(!Rc::ptr_eq(a, b) || i != j)),
 
             (i, j) => i.equals(j)?.negate()
         }
     }
 
     pub fn less(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__lt__", "__gt__") {
+            return result;
+        }
+
         match (self, other) {
             (Self::Integer(i), Self::Integer(j)) => Ok(Self::Boolean(*i < *j)),
 
@@ -251,8 +609,8 @@ impl Object {
 
             (Self::Float(i), Self::Float(j)) => Ok(Self::Boolean(*i < *j)),
 
-            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(*i < (*j as f64))),
-            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean((*i as f64) < *j)),
+            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(compare_integer_float(*j, *i) == Some(Ordering::Greater))),
+            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean(compare_integer_float(*i, *j) == Some(Ordering::Less))),
 
             _ => Err(SantaError::InvalidOperationError {
                 cause: format!(
@@ -264,6 +622,10 @@ impl Object {
     }
 
     pub fn greater(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__gt__", "__lt__") {
+            return result;
+        }
+
         match (self, other) {
             (Self::Integer(i), Self::Integer(j)) => Ok(Self::Boolean(*i > *j)),
 
@@ -272,8 +634,8 @@ impl Object {
 
             (Self::Float(i), Self::Float(j)) => Ok(Self::Boolean(*i > *j)),
 
-            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(*i > *j as f64)),
-            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean(*i as f64 > *j)),
+            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(compare_integer_float(*j, *i) == Some(Ordering::Less))),
+            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean(compare_integer_float(*i, *j) == Some(Ordering::Greater))),
 
             _ => Err(SantaError::InvalidOperationError {
                 cause: format!(
@@ -285,6 +647,10 @@ impl Object {
     }
 
     pub fn lessequals(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__le__", "__ge__") {
+            return result;
+        }
+
         match (self, other) {
             (Self::Integer(i), Self::Integer(j)) => Ok(Self::Boolean(*i <= *j)),
 
@@ -293,8 +659,8 @@ impl Object {
 
             (Self::Float(i), Self::Float(j)) => Ok(Self::Boolean(*i <= *j)),
 
-            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(*i <= *j as f64)),
-            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean(*i as f64 <= *j)),
+            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(matches!(compare_integer_float(*j, *i), Some(Ordering::Greater) | Some(Ordering::Equal)))),
+            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean(matches!(compare_integer_float(*i, *j), Some(Ordering::Less) | Some(Ordering::Equal)))),
 
             _ => Err(SantaError::InvalidOperationError {
                 cause: format!(
@@ -306,6 +672,10 @@ impl Object {
     }
 
     pub fn greaterequals(&self, other: &Object) -> Result<Object, SantaError> {
+        if let Some(result) = self.operator_hook(other, "__ge__", "__le__") {
+            return result;
+        }
+
         match (self, other) {
             (Self::Integer(i), Self::Integer(j)) => Ok(Self::Boolean(*i >= *j)),
 
@@ -314,8 +684,8 @@ impl Object {
 
             (Self::Float(i), Self::Float(j)) => Ok(Self::Boolean(*i >= *j)),
 
-            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(*i >= *j as f64)),
-            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean(*i as f64 >= *j)),
+            (Self::Float(i), Self::Integer(j)) => Ok(Self::Boolean(matches!(compare_integer_float(*j, *i), Some(Ordering::Less) | Some(Ordering::Equal)))),
+            (Self::Integer(i), Self::Float(j)) => Ok(Self::Boolean(matches!(compare_integer_float(*i, *j), Some(Ordering::Greater) | Some(Ordering::Equal)))),
 
             _ => Err(SantaError::InvalidOperationError {
                 cause: format!(
@@ -328,10 +698,30 @@ impl Object {
 
     pub fn index(&self, other: &Object) -> Result<Object, SantaError> {
         match (self, other) {
-            (Self::String(i), Self::Integer(j)) => Ok(Self::String(i.chars().nth(*j as usize).ok_or(SantaError::IndexOutOfBounds)?.to_string())),
-            (Self::List(i), Self::Integer(j)) => Ok(i.borrow().get(*j as usize).ok_or(SantaError::IndexOutOfBounds)?.clone()),
+            (Self::String(i), Self::Integer(j)) => {
+                let index = list_index(*j, i.chars().count())?;
+                Ok(Self::String(i.chars().nth(index).ok_or(SantaError::IndexOutOfBounds)?.to_string()))
+            }
+            (Self::List(i), Self::Integer(j)) => {
+                let list = i.borrow();
+                Ok(list[list_index(*j, list.len())?].clone())
+            }
 
-            (Self::Map(i), j) => Ok(i.borrow().get(j).ok_or(SantaError::KeyError)?.clone()),
+            (Self::String(i), Self::Slice(start, end, step)) => {
+                let chars: Vec<char> = i.chars().collect();
+                let indices = slice_indices(*start, *end, *step, chars.len())?;
+                Ok(Self::String(indices.into_iter().map(|i| chars[i]).collect()))
+            }
+            (Self::List(i), Self::Slice(start, end, step)) => {
+                let list = i.borrow();
+                let indices = slice_indices(*start, *end, *step, list.len())?;
+                Ok(vec_to_list(indices.into_iter().map(|i| list[i].clone()).collect()))
+            }
+
+            (Self::Map(i), j) => {
+                j.check_hashable()?;
+                Ok(i.borrow().get(j).ok_or(SantaError::KeyError)?.clone())
+            }
 
 
             // Blanket impl for booleans to work as integers
@@ -346,19 +736,102 @@ impl Object {
         }
     }
 
+    /// Yeets back an iterator over the values a for loop visits: the items of a list,
+    /// the characters of a string, the keys of a map or the integers of a range.
+    /// Lists and maps are copied first so the loop body can modify them safely.
+    pub fn iterate(&self) -> Result<Box<dyn Iterator<Item = Object>>, SantaError> {
+        match self {
+            Self::List(i) => Ok(Box::new(i.borrow().clone().into_iter())),
+            Self::String(i) => Ok(Box::new(
+                i.chars().map(|c| Self::String(c.to_string())).collect::<Vec<_>>().into_iter()
+            )),
+            Self::Map(i) => Ok(Box::new(i.borrow().keys().cloned().collect::<Vec<_>>().into_iter())),
+            Self::Range(start, stop, step) => {
+                let (stop, step) = (*stop, *step);
+                Ok(Box::new(
+                    std::iter::successors(Some(*start), move |i| i.checked_add(step))
+                        .take_while(move |i| if step > 0 { *i < stop } else { *i > stop })
+                        .map(Self::Integer)
+                ))
+            },
+
+            _ => Err(SantaError::InvalidOperationError {
+                cause: format!("iterating over {:?} not supported", self),
+            }),
+        }
+    }
+
+    /// Finds the position of the field `name` in a struct, which is also where its value is stored.
+    fn field_position(&self, name: &str) -> Result<(&RefCell<Vec<Object>>, usize), SantaError> {
+        match self {
+            Self::Struct(structtype, values) => match structtype.fields.iter().position(|i| i == name) {
+                Some(position) => Ok((values, position)),
+                None => Err(SantaError::InvalidOperationError {
+                    cause: format!("{} has no field {}", structtype.name, name),
+                }),
+            },
+            _ => Err(SantaError::InvalidOperationError {
+                cause: format!("getting field {} of {:?} not supported, only structs have fields", name, self),
+            }),
+        }
+    }
+
+    pub fn get_field(&self, name: &str) -> Result<Object, SantaError> {
+        let (values, position) = self.field_position(name)?;
+        let value = values.borrow()[position].clone();
+        Ok(value)
+    }
+
+    pub fn set_field(&self, name: &str, value: &Object) -> Result<(), SantaError> {
+        let (values, position) = self.field_position(name)?;
+        values.borrow_mut()[position] = value.clone();
+        Ok(())
+    }
+
     pub fn setindex(&self, other: &Object, value: &Object) -> Result<(), SantaError> {
         match (self, other) {
             (Self::List(i), Self::Integer(j)) => {
-                if *j as usize >= i.borrow().len() {
-                    return Err(SantaError::IndexOutOfBounds);
-                }
+                let index = list_index(*j, i.borrow().len())?;
+                i.borrow_mut()[index] = value.clone();
 
-                i.borrow_mut()[*j as usize] = value.clone();
+                Ok(())
+            },
+
+            (Self::List(i), Self::Slice(start, end, step)) => {
+                // Collect the new items first, the value could be the list itself
+                let values: Vec<Object> = value.iterate()?.collect();
+                let mut list = i.borrow_mut();
+
+                if step.unwrap_or(1) == 1 {
+                    // A simple slice is replaced, and the list grows or shrinks to fit the new items
+                    let len = list.len() as i64;
+                    let clamp = |bound: i64| (if bound < 0 { bound + len } else { bound }).max(0).min(len) as usize;
+                    let start = start.map_or(0, clamp);
+                    let end = end.map_or(list.len(), clamp).max(start);
+
+                    list.splice(start..end, values);
+                } else {
+                    let indices = slice_indices(*start, *end, *step, list.len())?;
+                    if indices.len() != values.len() {
+                        return Err(SantaError::InvalidOperationError {
+                            cause: format!(
+                                "can't assign {} items to a slice with step of {} items",
+                                values.len(),
+                                indices.len()
+                            ),
+                        });
+                    }
+
+                    for (index, value) in indices.into_iter().zip(values) {
+                        list[index] = value;
+                    }
+                }
 
                 Ok(())
             },
 
             (Self::Map(i), j) => {
+                j.check_hashable()?;
                 i.borrow_mut().insert(j.clone(), value.clone());
 
                 Ok(())
@@ -379,6 +852,63 @@ impl Object {
 }
 
 
+/// Turns an index into a list or string into a position, where negative indices count from the end.
+fn list_index(index: i64, len: usize) -> Result<usize, SantaError> {
+    let position = if index < 0 { index + len as i64 } else { index };
+
+    if position < 0 || position >= len as i64 {
+        Err(SantaError::IndexOutOfBounds)
+    } else {
+        Ok(position as usize)
+    }
+}
+
+/// Yeets back the positions a slice selects in a list or string of length `len`, in order.
+/// Negative bounds count from the end, and bounds outside of the list are moved to its edge.
+fn slice_indices(start: Option<i64>, end: Option<i64>, step: Option<i64>, len: usize) -> Result<Vec<usize>, SantaError> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    if step == 0 {
+        return Err(SantaError::InvalidOperationError {
+            cause: "The step of a slice can't be zero".into(),
+        });
+    }
+
+    // Going backwards, the slice can end just before the first item, at -1
+    let (lowest, highest) = if step > 0 { (0, len) } else { (-1, len - 1) };
+    let clamp = |bound: i64| (if bound < 0 { bound + len } else { bound }).max(lowest).min(highest);
+
+    let start = start.map_or(if step > 0 { 0 } else { len - 1 }, clamp);
+    let end = end.map_or(if step > 0 { len } else { -1 }, clamp);
+
+    Ok(std::iter::successors(Some(start), |i| i.checked_add(step))
+        .take_while(|i| if step > 0 { *i < end } else { *i > end })
+        .map(|i| i as usize)
+        .collect())
+}
+
+/// Compares an integer with a float without first rounding the integer to a float,
+/// which would make large integers compare equal to their neighbours.
+/// Yeets back `None` when the float is NaN.
+fn compare_integer_float(i: i64, j: f64) -> Option<Ordering> {
+    // 2^63, the first float too large to fit in an integer
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+
+    if j.is_nan() {
+        None
+    } else if j >= LIMIT {
+        Some(Ordering::Less)
+    } else if j < -LIMIT {
+        Some(Ordering::Greater)
+    } else {
+        // The float is in range, so its integer part can be converted exactly
+        match i.cmp(&(j.trunc() as i64)) {
+            Ordering::Equal => 0.0.partial_cmp(&j.fract()),
+            ordering => Some(ordering),
+        }
+    }
+}
+
 pub fn vec_to_list(values: Vec<Object>) -> Object {
     Object::List(Rc::new(RefCell::new(values)))
 }
\ No newline at end of file
@@ -1,9 +1,15 @@
 use crate::error::SantaError;
-use crate::function::ParameterList;
+use crate::format::FormatSpec;
+use crate::function::{Parameter, ParameterList};
+use crate::span::{Source, Span};
 use pest::iterators::Pair;
 use pest::Parser;
 use pest_derive;
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
 use std::process::exit;
+use std::rc::Rc;
 
 #[derive(pest_derive::Parser)]
 #[grammar = "grammar.pest"]
@@ -15,6 +21,15 @@ pub enum BinaryOperator {
     Subtract,
     Multiply,
     Divide,
+    FloorDivide,
+    Modulo,
+    Power,
+
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
 
     Equals,
     NotEquals,
@@ -23,12 +38,137 @@ pub enum BinaryOperator {
     GreaterEquals,
     LessEquals,
 
+    And,
+    Or,
+
     Index,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum UnaryOperator {
     Negate,
+    Not,
+}
+
+/// An argument in a function call.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Argument {
+    Positional(Box<AstNode>),
+    /// An argument given by name, like `b: 5`.
+    Keyword(String, Box<AstNode>),
+    /// Passes the items of a list (or anything else that can be iterated) as positional arguments, like `*list`.
+    Spread(Box<AstNode>),
+    /// Passes the items of a map as keyword arguments, like `**map`.
+    KeywordSpread(Box<AstNode>),
+}
+
+/// The names a destructuring assignment like `[id, name, *rest] = record;` assigns to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    Name(String),
+    /// Matches the items of a list. The `*rest` item (a name or `_`), if there is one, gets a list
+    /// of the items that are left over between the patterns before and after it.
+    List {
+        before: Vec<Pattern>,
+        rest: Option<Box<Pattern>>,
+        after: Vec<Pattern>,
+    },
+    /// Matches the values of string keys in a map. The `**rest` item, if there is one, gets a map
+    /// of the keys that weren't matched.
+    Map {
+        entries: Vec<(String, Pattern)>,
+        rest: Option<Box<Pattern>>,
+    },
+    /// `_`, which matches anything without assigning it to a name.
+    Wildcard,
+    /// A value like `3` or `"add"` in a match, which only matches values equal to it.
+    Literal(Box<AstNode>),
+}
+
+impl Pattern {
+    /// Yeets back all the names this pattern assigns to.
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            Pattern::Name(name) => vec![name.clone()],
+            Pattern::List { before, rest, after } => before
+                .iter()
+                .chain(after)
+                .chain(rest.as_deref())
+                .flat_map(Pattern::names)
+                .collect(),
+            Pattern::Map { entries, rest } => entries
+                .iter()
+                .map(|(_, pattern)| pattern)
+                .chain(rest.as_deref())
+                .flat_map(Pattern::names)
+                .collect(),
+            Pattern::Wildcard | Pattern::Literal(_) => vec![],
+        }
+    }
+
+    /// Whether the pattern contains literals, which can make it fail to match.
+    pub fn has_literals(&self) -> bool {
+        match self {
+            Pattern::Name(_) | Pattern::Wildcard => false,
+            Pattern::Literal(_) => true,
+            Pattern::List { before, after, .. } => before.iter().chain(after).any(Pattern::has_literals),
+            Pattern::Map { entries, .. } => entries.iter().any(|(_, pattern)| pattern.has_literals()),
+        }
+    }
+}
+
+/// Writes the pattern the way it looks in the source, like `[a, *rest]` or `{name, nose: color}`.
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Pattern::Name(name) => write!(f, "{}", name),
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Literal(literal) => write!(f, "{:?}", literal),
+            Pattern::List { before, rest, after } => {
+                let items: Vec<String> = before
+                    .iter()
+                    .map(Pattern::to_string)
+                    .chain(rest.iter().map(|rest| format!("*{}", rest)))
+                    .chain(after.iter().map(Pattern::to_string))
+                    .collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Pattern::Map { entries, rest } => {
+                let items: Vec<String> = entries
+                    .iter()
+                    .map(|(key, pattern)| match pattern {
+                        Pattern::Name(name) if name == key => key.clone(),
+                        pattern => format!("{}: {}", key, pattern),
+                    })
+                    .chain(rest.iter().map(|rest| format!("**{}", rest)))
+                    .collect();
+                write!(f, "{{{}}}", items.join(", "))
+            }
+        }
+    }
+}
+
+/// One step in the path to the thing that is assigned to, like the `[0]` and `.name` in `a[0].name = x;`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Accessor {
+    Index(Box<AstNode>),
+    Field(String),
+}
+
+/// A piece of an interpolated string like `f"id {id:>4}"`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FormatPart {
+    Text(String),
+    /// An expression between braces, formatted the way the specification after its `:` says.
+    Value(Box<AstNode>, FormatSpec),
+}
+
+/// One `pattern if guard => code` arm of a match expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Box<AstNode>>,
+    pub code: Vec<Box<AstNode>>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -44,18 +184,22 @@ pub enum Operator {
     },
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// The name, parameters and code of a method of a struct.
+pub type Method = (String, Rc<ParameterList>, Vec<Box<AstNode>>);
+
+#[derive(Clone, Debug)]
 pub enum AstNode {
     Integer(i64),
     Float(f64),
     Boolean(bool),
     Name(String),
     String(String),
+    FormatString(Vec<FormatPart>),
     List(Vec<Box<AstNode>>),
     Map(Vec<(Box<AstNode>, Box<AstNode>)>),
     Function {
         name: Box<AstNode>,
-        parameterlist: ParameterList,
+        parameterlist: Rc<ParameterList>,
         code: Vec<Box<AstNode>>,
     },
     IfStatement {
@@ -67,24 +211,214 @@ pub enum AstNode {
         condition: Box<AstNode>,
         code: Vec<Box<AstNode>>,
     },
+    ForLoop {
+        variable: Box<AstNode>,
+        iterable: Box<AstNode>,
+        code: Vec<Box<AstNode>>,
+    },
     Expression(Operator),
     Assignment {
         name: Box<AstNode>,
         expression: Box<AstNode>,
-        indexes: Vec<Box<AstNode>>,
+        indexes: Vec<Accessor>,
+    },
+    /// An assignment like `a[i] += 1;` that combines the old value with a new one.
+    CompoundAssignment {
+        name: Box<AstNode>,
+        operator: BinaryOperator,
+        expression: Box<AstNode>,
+        indexes: Vec<Accessor>,
+    },
+    /// Gets a field of a struct, like `child.name`.
+    Field {
+        value: Box<AstNode>,
+        name: String,
+    },
+    /// Defines a struct type with these fields and methods, like `struct Child { name, isnaughty }`.
+    Struct {
+        name: String,
+        fields: Vec<String>,
+        methods: Vec<Method>,
     },
     Functioncall {
         value: Box<AstNode>,
-        args: Vec<Box<AstNode>>,
+        args: Vec<Argument>,
+    },
+    /// Calls a method of a value, like `children.push(child)`.
+    MethodCall {
+        value: Box<AstNode>,
+        name: String,
+        args: Vec<Argument>,
+    },
+    /// Evaluates the code of the first arm whose pattern matches the value (and whose guard is true).
+    Match {
+        value: Box<AstNode>,
+        arms: Vec<MatchArm>,
+    },
+    TryStatement {
+        code: Vec<Box<AstNode>>,
+        errorname: Option<Box<AstNode>>,
+        catchcode: Option<Vec<Box<AstNode>>>,
+        finallycode: Option<Vec<Box<AstNode>>>,
+    },
+    Raise(Box<AstNode>),
+    /// The `start:end:step` in `a[start:end:step]`, where every part is optional.
+    Slice {
+        start: Option<Box<AstNode>>,
+        end: Option<Box<AstNode>>,
+        step: Option<Box<AstNode>>,
+    },
+    /// A list or map pattern on the left hand side of an assignment or in a for loop.
+    Pattern(Pattern),
+    /// Declares a variable in the current scope, even if a parent scope has one with the same name.
+    Let {
+        name: Box<AstNode>,
+        expression: Box<AstNode>,
+    },
+    /// Declares a variable in the current scope that can't be assigned to again.
+    Const {
+        name: Box<AstNode>,
+        expression: Box<AstNode>,
+    },
+    Global(Vec<String>),
+    Nonlocal(Vec<String>),
+    Strict,
+    /// Imports the names from the module at `path`, or all of them if no names are given.
+    Import {
+        path: String,
+        names: Option<Vec<String>>,
     },
     Return(Box<AstNode>),
+    Break,
+    Continue,
     None,
+    /// Marks the part of the source code a node was parsed from.
+    Spanned(Span, Box<AstNode>),
 }
 
 impl AstNode {
     pub fn boxed(self) -> Box<AstNode> {
         Box::new(self)
     }
+
+    pub fn spanned(self, span: Span) -> Box<AstNode> {
+        AstNode::Spanned(span, self.boxed()).boxed()
+    }
+
+    /// Yeets back the node without the information about where it was parsed from.
+    pub fn unspanned(&self) -> &AstNode {
+        match self {
+            AstNode::Spanned(_, node) => node.unspanned(),
+            node => node,
+        }
+    }
+}
+
+/// Nodes are equal when they have the same structure, no matter where in the source code they were parsed from.
+impl PartialEq for AstNode {
+    fn eq(&self, other: &Self) -> bool {
+        let other = other.unspanned();
+        match self.unspanned() {
+            AstNode::Integer(a) => matches!(other, AstNode::Integer(b) if a == b),
+            AstNode::Float(a) => matches!(other, AstNode::Float(b) if a == b),
+            AstNode::Boolean(a) => matches!(other, AstNode::Boolean(b) if a == b),
+            AstNode::Name(a) => matches!(other, AstNode::Name(b) if a == b),
+            AstNode::String(a) => matches!(other, AstNode::String(b) if a == b),
+            AstNode::FormatString(a) => matches!(other, AstNode::FormatString(b) if a == b),
+            AstNode::List(a) => matches!(other, AstNode::List(b) if a == b),
+            AstNode::Map(a) => matches!(other, AstNode::Map(b) if a == b),
+            AstNode::Function { name, parameterlist, code } => matches!(
+                other,
+                AstNode::Function { name: n, parameterlist: p, code: c } if name == n && parameterlist == p && code == c
+            ),
+            AstNode::IfStatement { condition, code, elsecode } => matches!(
+                other,
+                AstNode::IfStatement { condition: c, code: i, elsecode: e } if condition == c && code == i && elsecode == e
+            ),
+            AstNode::WhileLoop { condition, code } => matches!(
+                other,
+                AstNode::WhileLoop { condition: c, code: i } if condition == c && code == i
+            ),
+            AstNode::ForLoop { variable, iterable, code } => matches!(
+                other,
+                AstNode::ForLoop { variable: v, iterable: i, code: c } if variable == v && iterable == i && code == c
+            ),
+            AstNode::Expression(a) => matches!(other, AstNode::Expression(b) if a == b),
+            AstNode::Assignment { name, expression, indexes } => matches!(
+                other,
+                AstNode::Assignment { name: n, expression: e, indexes: i } if name == n && expression == e && indexes == i
+            ),
+            AstNode::CompoundAssignment { name, operator, expression, indexes } => matches!(
+                other,
+                AstNode::CompoundAssignment { name: n, operator: o, expression: e, indexes: i }
+                    if name == n && operator == o && expression == e && indexes == i
+            ),
+            AstNode::Field { value, name } => matches!(other, AstNode::Field { value: v, name: n } if value == v && name == n),
+            AstNode::Struct { name, fields, methods } => matches!(
+                other,
+                AstNode::Struct { name: n, fields: f, methods: m } if name == n && fields == f && methods == m
+            ),
+            AstNode::Functioncall { value, args } => matches!(
+                other,
+                AstNode::Functioncall { value: v, args: a } if value == v && args == a
+            ),
+            AstNode::MethodCall { value, name, args } => matches!(
+                other,
+                AstNode::MethodCall { value: v, name: n, args: a } if value == v && name == n && args == a
+            ),
+            AstNode::Match { value, arms } => matches!(other, AstNode::Match { value: v, arms: a } if value == v && arms == a),
+            AstNode::TryStatement { code, errorname, catchcode, finallycode } => matches!(
+                other,
+                AstNode::TryStatement { code: c, errorname: e, catchcode: cc, finallycode: f }
+                    if code == c && errorname == e && catchcode == cc && finallycode == f
+            ),
+            AstNode::Raise(a) => matches!(other, AstNode::Raise(b) if a == b),
+            AstNode::Slice { start, end, step } => matches!(
+                other,
+                AstNode::Slice { start: a, end: b, step: c } if start == a && end == b && step == c
+            ),
+            AstNode::Let { name, expression } => matches!(
+                other,
+                AstNode::Let { name: n, expression: e } if name == n && expression == e
+            ),
+            AstNode::Const { name, expression } => matches!(
+                other,
+                AstNode::Const { name: n, expression: e } if name == n && expression == e
+            ),
+            AstNode::Global(a) => matches!(other, AstNode::Global(b) if a == b),
+            AstNode::Nonlocal(a) => matches!(other, AstNode::Nonlocal(b) if a == b),
+            AstNode::Strict => matches!(other, AstNode::Strict),
+            AstNode::Import { path, names } => matches!(
+                other,
+                AstNode::Import { path: p, names: n } if path == p && names == n
+            ),
+            AstNode::Pattern(a) => matches!(other, AstNode::Pattern(b) if a == b),
+            AstNode::Return(a) => matches!(other, AstNode::Return(b) if a == b),
+            AstNode::Break => matches!(other, AstNode::Break),
+            AstNode::Continue => matches!(other, AstNode::Continue),
+            AstNode::None => matches!(other, AstNode::None),
+            AstNode::Spanned(..) => unreachable!("unspanned never yeets back a span"),
+        }
+    }
+}
+
+thread_local! {
+    /// The source that is being parsed, so spans can refer to it without
+    /// passing it along to every function in this file.
+    static SOURCE: RefCell<Option<Rc<Source>>> = const { RefCell::new(None) };
+}
+
+fn span_between(start: &Pair<Rule>, end: &Pair<Rule>) -> Span {
+    let source = SOURCE.with(|i| i.borrow().clone()).expect("spans can only be made while parsing");
+    let (start, end) = (start.as_span().start(), end.as_span().end());
+    // Pairs can end in the whitespace skipped after them, which shouldn't be underlined
+    let end = start + source.text[start..end].trim_end().len();
+
+    Span::new(source, start, end)
+}
+
+fn span_of(pair: &Pair<Rule>) -> Span {
+    span_between(pair, pair)
 }
 
 fn integer_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
@@ -124,25 +458,138 @@ fn name_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
     Ok(AstNode::Name(pair.as_str().into()).boxed())
 }
 
-fn vararg_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
-    Ok(AstNode::Name(
-        String::from("*") + pair
-            .into_inner()
-            .next().ok_or(SantaError::ParseTreeError { cause: "Couldn't parse".into()})?
-            .as_str()
-            .into()
-    ).boxed())
+/// Decodes the escape sequences in the contents of a string literal. If `quote` is
+/// given, a doubled quote character (`""` or `''`) stands for a single one.
+fn unescape(contents: &str, quote: Option<char>) -> Result<String, SantaError> {
+    let mut result = String::new();
+    let mut chars = contents.chars();
+
+    while let Some(c) = chars.next() {
+        if Some(c) == quote {
+            // The grammar only allows quotes to appear in pairs
+            chars.next();
+            result.push(c);
+            continue;
+        }
+
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        let escaped = chars.next().ok_or(SantaError::ParseError {
+            cause: "String ends with an unfinished escape".into(),
+        })?;
+
+        result.push(match escaped {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            'u' => {
+                let invalid = || SantaError::ParseError {
+                    cause: "Invalid unicode escape, expected something like \\u{1F385}".into(),
+                };
+
+                if chars.next() != Some('{') {
+                    return Err(invalid());
+                }
+
+                let mut digits = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => digits.push(c),
+                        None => return Err(invalid()),
+                    }
+                }
+
+                if digits.is_empty() || digits.len() > 6 {
+                    return Err(invalid());
+                }
+
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(std::char::from_u32)
+                    .ok_or_else(invalid)?
+            }
+            other => {
+                return Err(SantaError::ParseError {
+                    cause: format!("Invalid escape sequence \\{} in string", other),
+                })
+            }
+        });
+    }
+
+    Ok(result)
 }
 
 fn string_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
-    Ok(AstNode::String(pair.as_str().replace(&['"', '\"'][..], "").into()).boxed())
+    let literal = pair.into_inner().next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse string".into(),
+    })?;
+
+    let text = literal.as_str();
+    let string = match literal.as_rule() {
+        Rule::rawstring => {
+            let quotes = if text.starts_with("r'''") || text.starts_with("r\"\"\"") { 3 } else { 1 };
+            text[1 + quotes..text.len() - quotes].to_string()
+        }
+        Rule::tsqstring | Rule::tdqstring => unescape(&text[3..text.len() - 3], None)?,
+        Rule::sqstring => unescape(&text[1..text.len() - 1], Some('\''))?,
+        Rule::dqstring => unescape(&text[1..text.len() - 1], Some('"'))?,
+        _ => {
+            return Err(SantaError::ParseTreeError {
+                cause: "Couldn't parse string".into(),
+            })
+        }
+    };
+
+    Ok(AstNode::String(string).boxed())
+}
+
+fn fstring_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let mut parts = vec![];
+
+    for part in pair.into_inner() {
+        match part.as_rule() {
+            Rule::fstringtext => parts.push(FormatPart::Text(unescape(part.as_str(), None)?)),
+            Rule::fstringbrace => parts.push(FormatPart::Text(part.as_str()[..1].into())),
+            Rule::interpolation => {
+                let mut inner_pair = part.into_inner();
+                let value = disjunction_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+                    cause: "Couldn't parse string".into(),
+                })?)?;
+
+                let spec = match inner_pair.next() {
+                    Some(spec) => {
+                        // The specification starts after the `:`
+                        FormatSpec::parse(&spec.as_str()[1..]).map_err(|cause| SantaError::ParseError { cause })?
+                    }
+                    None => FormatSpec::default(),
+                };
+
+                parts.push(FormatPart::Value(value, spec));
+            }
+            _ => {
+                return Err(SantaError::ParseTreeError {
+                    cause: "Couldn't parse string".into(),
+                })
+            }
+        }
+    }
+
+    Ok(AstNode::FormatString(parts).boxed())
 }
 
 fn list_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
     let mut result = vec![];
     let mut inner_pair = pair.into_inner();
     while let Some(i) = inner_pair.next() {
-        result.push(comparison_to_ast(i)?);
+        result.push(disjunction_to_ast(i)?);
     }
 
     Ok(AstNode::List(result).boxed())
@@ -151,11 +598,11 @@ fn list_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
 fn pair_to_ast(pair: Pair<Rule>) -> Result<(Box<AstNode>, Box<AstNode>), SantaError> {
     let mut inner_pair = pair.into_inner();
 
-    let left = comparison_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+    let left = disjunction_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
         cause: "Couldn't parse to integer".into(),
     })?)?;
 
-    let right = comparison_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+    let right = disjunction_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
         cause: "Couldn't parse to integer".into(),
     })?)?;
 
@@ -173,17 +620,166 @@ fn map_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
 }
 
 fn return_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
-    Ok(Box::new(AstNode::Return(comparison_to_ast(
+    Ok(Box::new(AstNode::Return(disjunction_to_ast(
         pair.into_inner().next().ok_or(SantaError::ParseTreeError {
             cause: "Couldn't parse to integer".into(),
         })?,
     )?)))
 }
 
+fn raise_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    // The first pair is the raise (or throw) keyword itself
+    Ok(AstNode::Raise(disjunction_to_ast(pair.into_inner().nth(1).ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?)
+    .boxed())
+}
+
+fn let_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    // The first pair is the let keyword itself
+    let mut inner_pair = pair.into_inner().skip(1);
+
+    let name = name_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?;
+
+    // Without a value, the variable starts out as None
+    let expression = match inner_pair.next() {
+        Some(next) if next.as_rule() == Rule::disjunction => disjunction_to_ast(next)?,
+        _ => AstNode::None.boxed(),
+    };
+
+    Ok(AstNode::Let { name, expression }.boxed())
+}
+
+fn const_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    // The first pair is the const keyword itself
+    let mut inner_pair = pair.into_inner().skip(1);
+
+    let name = name_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?;
+
+    let expression = disjunction_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?;
+
+    Ok(AstNode::Const { name, expression }.boxed())
+}
+
+fn declaration_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let mut inner_pair = pair.into_inner();
+
+    let keyword = inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?;
+
+    let names = inner_pair
+        .filter(|i| i.as_rule() == Rule::name)
+        .map(|i| i.as_str().to_string())
+        .collect();
+
+    match keyword.as_rule() {
+        Rule::global => Ok(AstNode::Global(names).boxed()),
+        Rule::nonlocal => Ok(AstNode::Nonlocal(names).boxed()),
+        _ => Err(SantaError::ParseTreeError {
+            cause: "Couldn't parse".into(),
+        }),
+    }
+}
+
+fn import_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    // The first pair is the import keyword itself
+    let mut inner_pair = pair.into_inner().skip(1);
+
+    let mut next = inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?;
+
+    let names = if next.as_rule() == Rule::importnames {
+        let names = next.into_inner().map(|i| i.as_str().to_string()).collect();
+        next = inner_pair.next().ok_or(SantaError::ParseTreeError {
+            cause: "Couldn't parse".into(),
+        })?;
+        Some(names)
+    } else {
+        None
+    };
+
+    let path = match *string_to_ast(next)? {
+        AstNode::String(path) => path,
+        _ => {
+            return Err(SantaError::ParseTreeError {
+                cause: "Couldn't parse".into(),
+            })
+        }
+    };
+
+    // Modules are found relative to the file that imports them
+    let importer = SOURCE.with(|i| i.borrow().as_ref().map(|i| i.name.clone())).unwrap_or_default();
+    let directory = Path::new(&importer).parent().unwrap_or_else(|| Path::new(""));
+
+    Ok(AstNode::Import {
+        path: directory.join(path).to_string_lossy().into(),
+        names,
+    }
+    .boxed())
+}
+
+fn trystatement_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let mut inner_pair = pair.into_inner();
+
+    let code = block_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?;
+
+    let mut errorname = None;
+    let mut catchcode = None;
+    let mut finallycode = None;
+
+    for clause in inner_pair {
+        match clause.as_rule() {
+            Rule::catchclause => {
+                let mut inner_clause = clause.into_inner();
+                errorname = Some(name_to_ast(inner_clause.next().ok_or(SantaError::ParseTreeError {
+                    cause: "Couldn't parse".into(),
+                })?)?);
+                catchcode = Some(block_to_ast(inner_clause.next().ok_or(SantaError::ParseTreeError {
+                    cause: "Couldn't parse".into(),
+                })?)?);
+            }
+            Rule::finallyclause => {
+                finallycode = Some(block_to_ast(clause.into_inner().next().ok_or(SantaError::ParseTreeError {
+                    cause: "Couldn't parse".into(),
+                })?)?);
+            }
+            _ => {
+                return Err(SantaError::ParseTreeError {
+                    cause: "Couldn't parse".into(),
+                })
+            }
+        }
+    }
+
+    if catchcode.is_none() && finallycode.is_none() {
+        return Err(SantaError::ParseError {
+            cause: "A try block needs a catch or a finally block".into(),
+        });
+    }
+
+    Ok(AstNode::TryStatement {
+        code,
+        errorname,
+        catchcode,
+        finallycode,
+    }
+    .boxed())
+}
+
 fn ifstatement_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
     let mut inner_pair = pair.into_inner();
 
-    let condition = comparison_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+    let condition = disjunction_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
         cause: "Couldn't parse".into(),
     })?)?;
 
@@ -191,12 +787,15 @@ fn ifstatement_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
         cause: "Couldn't parse".into(),
     })?)?;
 
-    let else_block;
-    if let Some(next) = inner_pair.next() {
-        else_block = Some(block_to_ast(next)?);
-    } else {
-        else_block = None;
-    }
+    // An `else if` is parsed as an else block with just the next if statement in it
+    let else_block = match inner_pair.next() {
+        Some(next) if next.as_rule() == Rule::ifstatement => {
+            let span = span_of(&next);
+            Some(vec![ifstatement_to_ast(next)?.spanned(span)])
+        }
+        Some(next) => Some(block_to_ast(next)?),
+        None => None,
+    };
 
     Ok(Box::new(AstNode::IfStatement {
         condition,
@@ -208,7 +807,7 @@ fn ifstatement_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
 fn whileloop_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
     let mut inner_pair = pair.into_inner();
 
-    let condition = comparison_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+    let condition = disjunction_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
         cause: "Couldn't parse".into(),
     })?)?;
 
@@ -222,6 +821,169 @@ fn whileloop_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
     }))
 }
 
+fn pattern_to_ast(pair: Pair<Rule>) -> Result<Pattern, SantaError> {
+    let invalid = |cause: &str| SantaError::ParseError { cause: cause.into() };
+    let rest_pattern = |pair: Pair<Rule>| -> Result<Box<Pattern>, SantaError> {
+        Ok(Box::new(pattern_to_ast(pair.into_inner().next().ok_or(SantaError::ParseTreeError {
+            cause: "Couldn't parse pattern".into(),
+        })?)?))
+    };
+
+    match pair.as_rule() {
+        Rule::name => Ok(Pattern::Name(pair.as_str().into())),
+        Rule::wildcard => Ok(Pattern::Wildcard),
+        Rule::literalpattern => {
+            let mut inner_pair = pair.into_inner();
+            let first = inner_pair.next().ok_or(SantaError::ParseTreeError {
+                cause: "Couldn't parse pattern".into(),
+            })?;
+
+            let literal = match first.as_rule() {
+                Rule::negate => AstNode::Expression(Operator::Unary {
+                    operator: UnaryOperator::Negate,
+                    expr: atom_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+                        cause: "Couldn't parse pattern".into(),
+                    })?)?,
+                })
+                .boxed(),
+                _ => atom_to_ast(first)?,
+            };
+
+            Ok(Pattern::Literal(literal))
+        }
+        Rule::listpattern => {
+            let (mut before, mut rest, mut after) = (vec![], None, vec![]);
+            for item in pair.into_inner() {
+                match item.as_rule() {
+                    Rule::restpattern if rest.is_some() => return Err(invalid("A list pattern can only have one *rest")),
+                    Rule::restpattern => rest = Some(rest_pattern(item)?),
+                    _ if rest.is_some() => after.push(pattern_to_ast(item)?),
+                    _ => before.push(pattern_to_ast(item)?),
+                }
+            }
+
+            Ok(Pattern::List { before, rest, after })
+        }
+        Rule::mappattern => {
+            let (mut entries, mut rest) = (vec![], None);
+            for item in pair.into_inner() {
+                if rest.is_some() {
+                    return Err(invalid("The **rest has to be the last part of a map pattern"));
+                }
+
+                match item.as_rule() {
+                    Rule::keywordrestpattern => rest = Some(rest_pattern(item)?),
+                    _ => {
+                        let mut inner_pair = item.into_inner();
+                        let key: String = inner_pair.next().ok_or(SantaError::ParseTreeError {
+                            cause: "Couldn't parse pattern".into(),
+                        })?.as_str().into();
+
+                        // `{name}` is short for `{name: name}`
+                        let pattern = match inner_pair.next() {
+                            Some(pattern) => pattern_to_ast(pattern)?,
+                            None => Pattern::Name(key.clone()),
+                        };
+                        entries.push((key, pattern));
+                    }
+                }
+            }
+
+            Ok(Pattern::Map { entries, rest })
+        }
+        _ => Err(SantaError::ParseTreeError {
+            cause: "Couldn't parse pattern".into(),
+        }),
+    }
+}
+
+/// Parses a pattern that has to match, so it can't contain literals.
+fn irrefutable_pattern_to_ast(pair: Pair<Rule>) -> Result<Pattern, SantaError> {
+    let pattern = pattern_to_ast(pair)?;
+    if pattern.has_literals() {
+        return Err(SantaError::ParseError {
+            cause: "Literals can only be used in the patterns of a match".into(),
+        });
+    }
+
+    Ok(pattern)
+}
+
+/// Parses the thing that is assigned to in an assignment or for loop, which is a name or a pattern.
+fn target_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    match pair.as_rule() {
+        Rule::name => name_to_ast(pair),
+        _ => Ok(AstNode::Pattern(irrefutable_pattern_to_ast(pair)?).boxed()),
+    }
+}
+
+fn match_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    // The first pair is the match keyword
+    let mut inner_pair = pair.into_inner().skip(1);
+
+    let value = disjunction_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?;
+
+    let mut arms = vec![];
+    for arm in inner_pair {
+        let mut arm_pair = arm.into_inner();
+        let pattern = pattern_to_ast(arm_pair.next().ok_or(SantaError::ParseTreeError {
+            cause: "Couldn't parse match arm".into(),
+        })?)?;
+
+        let mut next = arm_pair.next().ok_or(SantaError::ParseTreeError {
+            cause: "Couldn't parse match arm".into(),
+        })?;
+
+        let guard = if next.as_rule() == Rule::matchguard {
+            // A guard is a disjunction that can't be a lambda
+            let guard = disjunction_to_ast(next)?;
+            next = arm_pair.next().ok_or(SantaError::ParseTreeError {
+                cause: "Couldn't parse match arm".into(),
+            })?;
+            Some(guard)
+        } else {
+            None
+        };
+
+        let code = match next.as_rule() {
+            Rule::block => block_to_ast(next)?,
+            _ => vec![disjunction_to_ast(next)?],
+        };
+
+        arms.push(MatchArm { pattern, guard, code });
+    }
+
+    Ok(AstNode::Match { value, arms }.boxed())
+}
+
+fn forloop_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    // The first pair is the for keyword
+    let mut inner_pair = pair.into_inner().skip(1);
+
+    let variable = target_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?;
+
+    // Then the in keyword
+    inner_pair.next();
+
+    let iterable = disjunction_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?;
+
+    let code_block = block_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?;
+
+    Ok(Box::new(AstNode::ForLoop {
+        variable,
+        iterable,
+        code: code_block,
+    }))
+}
+
 fn function_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
     let mut inner_pair = pair.into_inner();
 
@@ -238,44 +1000,60 @@ fn function_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
         (Box::new(AstNode::None), possible_name)
     };
 
-    let (parameterlist, block) = if possible_parameterlist.as_rule() == Rule::parameterlist {
+    let (real_parameterlist, block) = if possible_parameterlist.as_rule() == Rule::parameterlist {
         let next = inner_pair.next().ok_or(SantaError::ParseTreeError {
             cause: "Couldn't parse".into(),
         })?;
         (parameterlist_to_ast(possible_parameterlist)?, next)
     } else {
-        (vec![], possible_parameterlist)
+        (ParameterList::empty(), possible_parameterlist)
     };
 
-    let mut parameters = Vec::new();
-
-    for i in parameterlist {
-        // can *only* be `name` nodes meaning that they can be
-        // evaluated directly into strings.
-        if let AstNode::Name(name) = *i {
-            parameters.push(name);
-        } else {
-            return Err(SantaError::ParseTreeError {
-                cause: "Couldn't parse parameter".into(),
-            });
-        }
-    }
-
-    let real_parameterlist = ParameterList::new(parameters);
-
     let function_block = block_to_ast(block)?;
 
     Ok(Box::new(AstNode::Function {
         name,
-        parameterlist: real_parameterlist,
+        parameterlist: Rc::new(real_parameterlist),
         code: function_block,
     }))
 }
 
+fn lambda_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let mut inner_pair = pair.into_inner();
+
+    let first = inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?;
+
+    let (parameterlist, body) = match first.as_rule() {
+        Rule::name => (ParameterList::new(vec![first.as_str().into()]), inner_pair.next()),
+        Rule::parameterlist => (parameterlist_to_ast(first)?, inner_pair.next()),
+        _ => (ParameterList::empty(), Some(first)),
+    };
+
+    let body = body.ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?;
+
+    let code = match body.as_rule() {
+        Rule::block => block_to_ast(body)?,
+        _ => vec![AstNode::Return(disjunction_to_ast(body)?).boxed()],
+    };
+
+    Ok(AstNode::Function {
+        name: AstNode::None.boxed(),
+        parameterlist: Rc::new(parameterlist),
+        code,
+    }
+    .boxed())
+}
+
 fn atom_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
     let r = pair.as_rule();
-    match r {
+    let span = span_of(&pair);
+    let atom = match r {
         Rule::ifstatement => ifstatement_to_ast(pair),
+        Rule::matchexpression => match_to_ast(pair),
         Rule::name => name_to_ast(pair),
         Rule::integer => integer_to_ast(pair),
         Rule::list => list_to_ast(pair),
@@ -283,54 +1061,207 @@ fn atom_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
         Rule::boolean => boolean_to_ast(pair),
         Rule::float => float_to_ast(pair),
         Rule::string => string_to_ast(pair),
-        Rule::comparison => comparison_to_ast(pair),
+        Rule::fstring => fstring_to_ast(pair),
+        Rule::disjunction => disjunction_to_ast(pair),
         Rule::function => function_to_ast(pair),
         _ => Err(SantaError::ParseTreeError {
             cause: "Not implemented".into(),
         }),
+    };
+
+    match r {
+        // The expression between parentheses already knows where it came from
+        Rule::disjunction => atom,
+        _ => atom.map(|i| i.spanned(span.clone())).map_err(|e| e.located(&span)),
     }
 }
 
-fn parameterlist_to_ast(pair: Pair<Rule>) -> Result<Vec<Box<AstNode>>, SantaError> {
-    let mut result = vec![];
+/// Parameters have to be in the order `a, b = 3, *rest, **opts`, where every part is optional.
+fn parameterlist_to_ast(pair: Pair<Rule>) -> Result<ParameterList, SantaError> {
+    let invalid = |cause: &str| SantaError::ParseError { cause: cause.into() };
+    let mut result = ParameterList::empty();
+    let mut names: Vec<String> = vec![];
 
     for param in pair.into_inner() {
-        match param.as_rule() {
-            Rule::name => {
-                result.push(name_to_ast(param)?)
+        let rule = param.as_rule();
+        // A pattern parameter has no name of its own, only the names it binds
+        let (name, mut inner_pair, pattern) = match rule {
+            Rule::name => (param.as_str().to_string(), param.into_inner(), None),
+            Rule::listpattern | Rule::mappattern => (String::new(), param.clone().into_inner(), Some(irrefutable_pattern_to_ast(param)?)),
+            _ => {
+                let mut inner_pair = param.into_inner();
+                let name = inner_pair.next().ok_or(SantaError::ParseTreeError {
+                    cause: "Couldn't parse parameter".into(),
+                })?;
+                (name.as_str().to_string(), inner_pair, None)
             }
-            Rule::vararg => {
-                result.push(vararg_to_ast(param)?);
+        };
+
+        let bound = pattern.as_ref().map_or_else(|| vec![name.clone()], Pattern::names);
+        for bound_name in bound {
+            if names.contains(&bound_name) {
+                return Err(invalid(&format!("Parameter {} is used more than once", bound_name)));
+            }
+            names.push(bound_name);
+        }
+
+        if result.keyword_rest.is_some() {
+            return Err(invalid("The **parameter has to be the last parameter"));
+        }
+
+        match rule {
+            Rule::keywordvararg => result.keyword_rest = Some(name),
+            _ if result.rest.is_some() => return Err(invalid("Only the **parameter can come after the *parameter")),
+            Rule::vararg => result.rest = Some(name),
+            Rule::defaultparameter => {
+                let default = disjunction_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+                    cause: "Couldn't parse parameter".into(),
+                })?)?;
+                result.defaults.insert(name.clone(), default);
+                result.positional.push(Parameter::Name(name));
+            }
+            Rule::name | Rule::listpattern | Rule::mappattern => {
+                if !result.defaults.is_empty() {
+                    return Err(invalid("Parameters without a default can't come after parameters with one"));
+                }
+                result.positional.push(match pattern {
+                    Some(pattern) => Parameter::Pattern(pattern),
+                    None => Parameter::Name(name),
+                });
+            }
+            _ => {
+                return Err(SantaError::ParseTreeError {
+                    cause: "Couldn't parse parameter".into(),
+                })
             }
-            _ => unreachable!()
         }
     }
 
     Ok(result)
 }
 
-fn argumentlist_to_ast(pair: Option<Pair<Rule>>) -> Result<Vec<Box<AstNode>>, SantaError> {
+fn argumentlist_to_ast(pair: Option<Pair<Rule>>) -> Result<Vec<Argument>, SantaError> {
+    let mut result = vec![];
+
     if let Some(pair) = pair {
-        let mut result = vec![];
+        for arg in pair.into_inner() {
+            match arg.as_rule() {
+                Rule::keywordargument => {
+                    let mut inner_pair = arg.into_inner();
+                    let name = inner_pair.next().ok_or(SantaError::ParseTreeError {
+                        cause: "Couldn't parse argument".into(),
+                    })?;
+                    let value = disjunction_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+                        cause: "Couldn't parse argument".into(),
+                    })?)?;
+
+                    result.push(Argument::Keyword(name.as_str().into(), value));
+                }
+                Rule::spreadargument | Rule::keywordspreadargument => {
+                    let rule = arg.as_rule();
+                    let value = disjunction_to_ast(arg.into_inner().next().ok_or(SantaError::ParseTreeError {
+                        cause: "Couldn't parse argument".into(),
+                    })?)?;
+
+                    result.push(match rule {
+                        Rule::spreadargument => Argument::Spread(value),
+                        _ => Argument::KeywordSpread(value),
+                    });
+                }
+                _ => {
+                    if result.iter().any(|i| matches!(i, Argument::Keyword(..) | Argument::KeywordSpread(..))) {
+                        return Err(SantaError::ParseError {
+                            cause: "Positional arguments can't come after keyword arguments".into(),
+                        });
+                    }
 
-        let innerpair = pair.into_inner();
-        for arg in innerpair {
-            result.push(comparison_to_ast(arg)?)
+                    result.push(Argument::Positional(disjunction_to_ast(arg)?));
+                }
+            }
         }
-
-        Ok(result)
-    } else {
-        Ok(vec![])
     }
+
+    Ok(result)
 }
 
 
 
 fn index_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
-    comparison_to_ast(pair)
+    if pair.as_rule() != Rule::slice {
+        return disjunction_to_ast(pair);
+    }
+
+    let (mut start, mut end, mut step) = (None, None, None);
+    for part in pair.into_inner() {
+        let rule = part.as_rule();
+        let value = Some(disjunction_to_ast(part.into_inner().next().ok_or(SantaError::ParseTreeError {
+            cause: "Couldn't parse slice".into(),
+        })?)?);
+
+        match rule {
+            Rule::slicestart => start = value,
+            Rule::sliceend => end = value,
+            _ => step = value,
+        }
+    }
+
+    Ok(AstNode::Slice { start, end, step }.boxed())
+}
+
+/// Yeets back the name in a `.name` field trailer.
+fn field_name(pair: Pair<Rule>) -> Result<String, SantaError> {
+    Ok(pair.into_inner().next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse field".into(),
+    })?.as_str().into())
+}
+
+fn struct_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    // The first pair is the struct keyword
+    let mut inner_pair = pair.into_inner().skip(1);
+
+    let name = inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?.as_str().to_string();
+
+    let mut fields: Vec<String> = vec![];
+    let mut methods = vec![];
+    for member in inner_pair {
+        if member.as_rule() == Rule::function {
+            let (method, parameterlist, code) = match *function_to_ast(member)? {
+                AstNode::Function { name: method, parameterlist, code } => match *method {
+                    AstNode::Name(method) => (method, parameterlist, code),
+                    _ => return Err(SantaError::ParseError {
+                        cause: format!("The methods of struct {} need a name", name),
+                    }),
+                },
+                _ => return Err(SantaError::ParseTreeError {
+                    cause: "Couldn't parse method".into(),
+                }),
+            };
+
+            if fields.contains(&method) || methods.iter().any(|(i, _, _)| i == &method) {
+                return Err(SantaError::ParseError {
+                    cause: format!("{} is defined more than once in struct {}", method, name),
+                });
+            }
+            methods.push((method, parameterlist, code));
+            continue;
+        }
+
+        let field = member.as_str().to_string();
+        if fields.contains(&field) {
+            return Err(SantaError::ParseError {
+                cause: format!("Field {} is used more than once in struct {}", field, name),
+            });
+        }
+        fields.push(field);
+    }
+
+    Ok(AstNode::Struct { name, fields, methods }.boxed())
 }
 
 fn atomexpr_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let start = pair.clone();
     let mut innerpair = pair.into_inner();
     let atom = atom_to_ast(innerpair.next().ok_or(SantaError::ParseTreeError {
         cause: "Couldn't parse".into(),
@@ -338,6 +1269,7 @@ fn atomexpr_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
 
     let mut res = atom;
     while let Some(i) = innerpair.next(){
+        let span = span_between(&start, &i);
         res = match i.as_rule() {
             Rule::functioncall => Box::new(AstNode::Functioncall {
                 value: res,
@@ -352,16 +1284,34 @@ fn atomexpr_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
                     })?)?
                 }
             )),
+            Rule::field => AstNode::Field {
+                value: res,
+                name: field_name(i)?,
+            }
+            .boxed(),
+            Rule::methodcall => {
+                let mut inner = i.into_inner();
+                AstNode::MethodCall {
+                    value: res,
+                    name: inner.next().ok_or(SantaError::ParseTreeError {
+                        cause: "Couldn't parse method call".into(),
+                    })?.as_str().into(),
+                    args: argumentlist_to_ast(inner.next())?,
+                }
+                .boxed()
+            }
             _ => return Err(SantaError::ParseTreeError {
                 cause: "Couldn't parse".into(),
             }),
         }
+        .spanned(span);
     };
 
     Ok(res)
 }
 
 fn factor_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let span = span_of(&pair);
     let mut inner_pair = pair.into_inner();
     let next = inner_pair.next().ok_or(SantaError::ParseTreeError {
         cause: "Couldn't parse".into(),
@@ -375,12 +1325,35 @@ fn factor_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
                 cause: "Couldn't parse".into(),
             })?)?,
         })
-        .boxed(),
-        _ => atomexpr_to_ast(next)?,
+        .spanned(span),
+        _ => power_to_ast(next)?,
     })
 }
 
+fn power_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let span = span_of(&pair);
+    let mut inner_pair = pair.into_inner();
+    let base = atomexpr_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?;
+
+    // Exponentiation is right associative: the exponent is a whole factor
+    if inner_pair.next().is_some() {
+        Ok(AstNode::Expression(Operator::Binary {
+            operator: BinaryOperator::Power,
+            lhs: base,
+            rhs: factor_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+                cause: "Couldn't parse".into(),
+            })?)?,
+        })
+        .spanned(span))
+    } else {
+        Ok(base)
+    }
+}
+
 fn term_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let start = pair.clone();
     let mut inner_pair = pair.into_inner();
 
     let mut result = factor_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
@@ -392,10 +1365,13 @@ fn term_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
             cause: "Couldn't parse".into(),
         })?;
 
+        let span = span_between(&start, &curr);
         let new_result = AstNode::Expression(Operator::Binary {
             operator: match operator.as_str() {
                 "*" => BinaryOperator::Multiply,
                 "/" => BinaryOperator::Divide,
+                "//" => BinaryOperator::FloorDivide,
+                "%" => BinaryOperator::Modulo,
                 _ => {
                     return Err(SantaError::ParseTreeError {
                         cause: "Invalid operator".into(),
@@ -405,7 +1381,7 @@ fn term_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
             lhs: result,
             rhs: factor_to_ast(curr)?,
         })
-        .boxed();
+        .spanned(span);
         result = new_result;
     }
 
@@ -413,6 +1389,7 @@ fn term_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
 }
 
 fn expression_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let start = pair.clone();
     let mut inner_pair = pair.into_inner();
 
     let mut result = term_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
@@ -424,6 +1401,7 @@ fn expression_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
             cause: "Couldn't parse".into(),
         })?;
 
+        let span = span_between(&start, &curr);
         let new_result = AstNode::Expression(Operator::Binary {
             operator: match operator.as_str() {
                 "+" => BinaryOperator::Add,
@@ -437,16 +1415,74 @@ fn expression_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
             lhs: result,
             rhs: term_to_ast(curr)?,
         })
-        .boxed();
+        .spanned(span);
         result = new_result;
     }
 
-    Ok(result.boxed())
+    Ok(result)
+}
+
+/// Builds a left associative chain of bitwise operations like `a << b >> c`,
+/// where the operands are parsed with `operand_to_ast`.
+fn bitwise_to_ast(
+    pair: Pair<Rule>,
+    operand_to_ast: fn(Pair<Rule>) -> Result<Box<AstNode>, SantaError>,
+) -> Result<Box<AstNode>, SantaError> {
+    let start = pair.clone();
+    let mut inner_pair = pair.into_inner();
+
+    let mut result = operand_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?;
+
+    while let Some(operator) = inner_pair.next() {
+        let curr = inner_pair.next().ok_or(SantaError::ParseTreeError {
+            cause: "Couldn't parse".into(),
+        })?;
+
+        let span = span_between(&start, &curr);
+        result = AstNode::Expression(Operator::Binary {
+            operator: match operator.as_rule() {
+                Rule::shiftleft => BinaryOperator::ShiftLeft,
+                Rule::shiftright => BinaryOperator::ShiftRight,
+                Rule::ampersand => BinaryOperator::BitAnd,
+                Rule::caret => BinaryOperator::BitXor,
+                Rule::pipe => BinaryOperator::BitOr,
+                _ => {
+                    return Err(SantaError::ParseTreeError {
+                        cause: "Invalid operator".into(),
+                    })
+                }
+            },
+            lhs: result,
+            rhs: operand_to_ast(curr)?,
+        })
+        .spanned(span);
+    }
+
+    Ok(result)
+}
+
+fn shift_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    bitwise_to_ast(pair, expression_to_ast)
+}
+
+fn bitand_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    bitwise_to_ast(pair, shift_to_ast)
+}
+
+fn bitxor_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    bitwise_to_ast(pair, bitand_to_ast)
+}
+
+fn bitor_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    bitwise_to_ast(pair, bitxor_to_ast)
 }
 
 fn comparison_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let span = span_of(&pair);
     let mut inner_pair = pair.into_inner();
-    let expr = expression_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+    let expr = bitor_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
         cause: "Couldn't parse".into(),
     })?)?;
 
@@ -465,61 +1501,205 @@ fn comparison_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
             }
         };
 
-        return Ok(Box::new(AstNode::Expression(Operator::Binary {
+        Ok(AstNode::Expression(Operator::Binary {
             operator: binop,
             lhs: expr,
-            rhs: expression_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+            rhs: bitor_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
                 cause: "Couldn't parse".into(),
             })?)?,
-        })));
+        })
+        .spanned(span))
     } else {
         Ok(expr)
     }
 }
 
-fn statement_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
-    let r = pair.as_rule();
-    match r {
-        Rule::assignment => {
-            let mut inner_pair = pair.into_inner();
-            let name = inner_pair.next().ok_or(SantaError::ParseTreeError {
+fn inversion_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let span = span_of(&pair);
+    let mut inner_pair = pair.into_inner();
+    let next = inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?;
+
+    match next.as_rule() {
+        Rule::not => Ok(AstNode::Expression(Operator::Unary {
+            operator: UnaryOperator::Not,
+            expr: inversion_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
                 cause: "Couldn't parse".into(),
-            })?;
+            })?)?,
+        })
+        .spanned(span)),
+        _ => comparison_to_ast(next),
+    }
+}
 
-            let mut indexes = vec![];
+fn conjunction_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let start = pair.clone();
+    let mut inner_pair = pair.into_inner();
 
-            let next_pair = loop {
-                let next_pair = inner_pair.next().ok_or(SantaError::ParseTreeError {
-                    cause: "Couldn't parse".into(),
-                })?;
+    let mut result = inversion_to_ast(inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?)?;
 
-                if next_pair.as_rule() != Rule::index {
-                    break next_pair;
-                }
+    while inner_pair.next().is_some() {
+        let curr = inner_pair.next().ok_or(SantaError::ParseTreeError {
+            cause: "Couldn't parse".into(),
+        })?;
 
-                indexes.push(index_to_ast(next_pair.into_inner().next().ok_or(SantaError::ParseTreeError {
-                    cause: "Couldn't parse".into(),
-                })?)?);
-            };
+        let span = span_between(&start, &curr);
+        result = AstNode::Expression(Operator::Binary {
+            operator: BinaryOperator::And,
+            lhs: result,
+            rhs: inversion_to_ast(curr)?,
+        })
+        .spanned(span);
+    }
+
+    Ok(result)
+}
+
+fn disjunction_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let start = pair.clone();
+    let mut inner_pair = pair.into_inner();
+
+    let first = inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?;
+    if first.as_rule() == Rule::lambda {
+        let span = span_of(&first);
+        return lambda_to_ast(first).map(|i| i.spanned(span.clone())).map_err(|e| e.located(&span));
+    }
+
+    let mut result = conjunction_to_ast(first)?;
+
+    while inner_pair.next().is_some() {
+        let curr = inner_pair.next().ok_or(SantaError::ParseTreeError {
+            cause: "Couldn't parse".into(),
+        })?;
+
+        let span = span_between(&start, &curr);
+        result = AstNode::Expression(Operator::Binary {
+            operator: BinaryOperator::Or,
+            lhs: result,
+            rhs: conjunction_to_ast(curr)?,
+        })
+        .spanned(span);
+    }
+
+    Ok(result)
+}
 
+fn assignment_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let mut inner_pair = pair.into_inner();
+    let name = inner_pair.next().ok_or(SantaError::ParseTreeError {
+        cause: "Couldn't parse".into(),
+    })?;
 
-            let expression = next_pair;
+    // Patterns can't be indexed or used with compound operators
+    if name.as_rule() != Rule::name {
+        let expression = inner_pair.next().ok_or(SantaError::ParseTreeError {
+            cause: "Couldn't parse".into(),
+        })?;
 
-            Ok(AstNode::Assignment {
-                name: name_to_ast(name)?,
-                expression: comparison_to_ast(expression)?,
-                indexes
+        return Ok(AstNode::Assignment {
+            name: target_to_ast(name)?,
+            expression: disjunction_to_ast(expression)?,
+            indexes: vec![],
+        }
+        .boxed());
+    }
+
+    let mut indexes = vec![];
+
+    let next_pair = loop {
+        let next_pair = inner_pair.next().ok_or(SantaError::ParseTreeError {
+            cause: "Couldn't parse".into(),
+        })?;
+
+        match next_pair.as_rule() {
+            Rule::index => indexes.push(Accessor::Index(index_to_ast(next_pair.into_inner().next().ok_or(SantaError::ParseTreeError {
+                cause: "Couldn't parse".into(),
+            })?)?)),
+            Rule::field => indexes.push(Accessor::Field(field_name(next_pair)?)),
+            _ => break next_pair,
+        }
+    };
+
+    if next_pair.as_rule() == Rule::compoundoperator {
+        let operator = match next_pair.as_str() {
+            "+=" => BinaryOperator::Add,
+            "-=" => BinaryOperator::Subtract,
+            "*=" => BinaryOperator::Multiply,
+            "/=" => BinaryOperator::Divide,
+            "//=" => BinaryOperator::FloorDivide,
+            "%=" => BinaryOperator::Modulo,
+            "**=" => BinaryOperator::Power,
+            "<<=" => BinaryOperator::ShiftLeft,
+            ">>=" => BinaryOperator::ShiftRight,
+            "&=" => BinaryOperator::BitAnd,
+            "^=" => BinaryOperator::BitXor,
+            "|=" => BinaryOperator::BitOr,
+            _ => {
+                return Err(SantaError::ParseTreeError {
+                    cause: "Invalid operator".into(),
+                })
             }
-            .boxed())
+        };
+
+        let expression = inner_pair.next().ok_or(SantaError::ParseTreeError {
+            cause: "Couldn't parse".into(),
+        })?;
+
+        Ok(AstNode::CompoundAssignment {
+            name: name_to_ast(name)?,
+            operator,
+            expression: disjunction_to_ast(expression)?,
+            indexes,
         }
-        Rule::comparison => comparison_to_ast(pair),
+        .boxed())
+    } else {
+        let expression = next_pair;
+
+        Ok(AstNode::Assignment {
+            name: name_to_ast(name)?,
+            expression: disjunction_to_ast(expression)?,
+            indexes
+        }
+        .boxed())
+    }
+}
+
+fn statement_to_ast(pair: Pair<Rule>) -> Result<Box<AstNode>, SantaError> {
+    let r = pair.as_rule();
+    let span = span_of(&pair);
+    let statement = match r {
+        Rule::assignment => assignment_to_ast(pair),
+        Rule::disjunction => disjunction_to_ast(pair),
         Rule::function => function_to_ast(pair),
+        Rule::structdefinition => struct_to_ast(pair),
         Rule::ifstatement => ifstatement_to_ast(pair),
+        Rule::matchexpression => match_to_ast(pair),
         Rule::whileloop => whileloop_to_ast(pair),
+        Rule::forloop => forloop_to_ast(pair),
         Rule::returnstatement => return_to_ast(pair),
+        Rule::raisestatement => raise_to_ast(pair),
+        Rule::importstatement => import_to_ast(pair),
+        Rule::letstatement => let_to_ast(pair),
+        Rule::conststatement => const_to_ast(pair),
+        Rule::declaration => declaration_to_ast(pair),
+        Rule::strictstatement => Ok(AstNode::Strict.boxed()),
+        Rule::trystatement => trystatement_to_ast(pair),
+        Rule::breakstatement => Ok(AstNode::Break.boxed()),
+        Rule::continuestatement => Ok(AstNode::Continue.boxed()),
         _ => Err(SantaError::ParseTreeError {
             cause: "Not implemented".into(),
         }),
+    };
+
+    match r {
+        // Expressions already know where they came from
+        Rule::disjunction => statement,
+        _ => statement.map(|i| i.spanned(span.clone())).map_err(|e| e.located(&span)),
     }
 }
 
@@ -553,9 +1733,27 @@ fn file_to_ast(pairs: Pair<Rule>) -> Result<Vec<Box<AstNode>>, SantaError> {
     Ok(ast)
 }
 
+#[allow(dead_code)]
 pub fn parse_string(input: &str) -> Result<Vec<Box<AstNode>>, SantaError> {
-    let mut pairs = SantaParser::parse(Rule::file, input).map_err(|e| SantaError::ParseError {
-        cause: format!("{}", e),
+    parse_source(Source::new("<string>", input))
+}
+
+/// Parses the contents of a file. The name is used to tell the user where errors are.
+pub fn parse_file(name: &str, input: &str) -> Result<Vec<Box<AstNode>>, SantaError> {
+    parse_source(Source::new(name, input))
+}
+
+fn parse_source(source: Rc<Source>) -> Result<Vec<Box<AstNode>>, SantaError> {
+    let previous = SOURCE.with(|i| i.replace(Some(source.clone())));
+    let result = parse_pairs(&source);
+    SOURCE.with(|i| i.replace(previous));
+
+    result
+}
+
+fn parse_pairs(source: &Source) -> Result<Vec<Box<AstNode>>, SantaError> {
+    let mut pairs = SantaParser::parse(Rule::file, &source.text).map_err(|e| SantaError::ParseError {
+        cause: format!("{}", e.with_path(&source.name)),
     })?;
 
     let first_pair = pairs.next().ok_or(SantaError::ParseTreeError {
@@ -570,6 +1768,7 @@ pub fn parse_string(input: &str) -> Result<Vec<Box<AstNode>>, SantaError> {
     }
 }
 
+#[allow(dead_code)]
 pub fn parse_string_or_panic(input: &str) -> Vec<Box<AstNode>> {
     match parse_string(input) {
         Ok(i) => i,
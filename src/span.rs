@@ -0,0 +1,63 @@
+use std::fmt::{Debug, Display, Error, Formatter};
+use std::rc::Rc;
+
+/// The text of a santa program together with the name of the file it was read from.
+#[derive(Debug, PartialEq)]
+pub struct Source {
+    pub name: String,
+    pub text: String,
+}
+
+impl Source {
+    pub fn new(name: &str, text: &str) -> Rc<Self> {
+        Rc::new(Self {
+            name: name.into(),
+            text: text.into(),
+        })
+    }
+}
+
+/// The part of a source file a node was parsed from, stored as byte offsets.
+#[derive(Clone)]
+pub struct Span {
+    pub source: Rc<Source>,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(source: Rc<Source>, start: usize, end: usize) -> Self {
+        Self { source, start, end }
+    }
+
+    /// Yeets back the line and column (both starting at 1) this span starts at.
+    pub fn line_col(&self) -> (usize, usize) {
+        let before = &self.source.text[..self.start];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+
+        (line, column)
+    }
+
+    /// Yeets back the full line of source code this span starts on.
+    pub fn line_text(&self) -> &str {
+        let text = &self.source.text;
+        let line_start = text[..self.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = text[self.start..].find('\n').map(|i| self.start + i).unwrap_or_else(|| text.len());
+
+        text[line_start..line_end].trim_end_matches('\r')
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let (line, column) = self.line_col();
+        write!(f, "{}:{}:{}", self.source.name, line, column)
+    }
+}
+
+impl Debug for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self)
+    }
+}